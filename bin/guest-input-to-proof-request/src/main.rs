@@ -4,6 +4,12 @@ use prover::ProofRequest;
 
 fn main() {
     let args = std::env::args().collect::<Vec<_>>();
+    // drop the witness which is not accessed by the block
+    let minimize = args.iter().skip(2).any(|arg| arg == "--minimize");
+    // bincode instead of json, for `Content-Type: application/x-bincode`
+    let binary = args.iter().skip(2).any(|arg| arg == "--binary");
+    // for `Content-Encoding: gzip`
+    let gzip = args.iter().skip(2).any(|arg| arg == "--gzip");
 
     let guest_input_path = PathBuf::new().join(&args[1]);
     let file_name = guest_input_path.file_name().unwrap().to_str().unwrap();
@@ -16,8 +22,28 @@ fn main() {
     let data = std::fs::read(&guest_input_path).unwrap();
     let guest_input = prover::read_guest_input(&data).unwrap();
 
-    let proof_input = prover::guest_input_to_proof_input(guest_input).unwrap();
-    let proof_request = serde_json::to_vec_pretty(&ProofRequest { input: proof_input }).unwrap();
+    let mut proof_input = prover::guest_input_to_proof_input(guest_input).unwrap();
+    if minimize {
+        proof_input = prover::minimize_proof_input(&proof_input).unwrap();
+    }
+    let proof_request = ProofRequest {
+        input: proof_input,
+        version: None,
+    };
+    let (mut proof_request, mut file_name) = match binary {
+        true => (
+            prover::encode_proof_request_bincode(&proof_request).unwrap(),
+            format!("{}.bincode", file_name.trim_end_matches(".json")),
+        ),
+        false => (
+            serde_json::to_vec_pretty(&proof_request).unwrap(),
+            file_name.to_owned(),
+        ),
+    };
+    if gzip {
+        proof_request = prover::gzip(&proof_request).unwrap();
+        file_name = format!("{}.gz", file_name);
+    }
 
     let dest = guest_input_path
         .parent()
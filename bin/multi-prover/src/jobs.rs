@@ -0,0 +1,483 @@
+use std::{
+    future::Future,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use actix_web::{
+    get, post,
+    rt::{spawn, task::spawn_blocking},
+    web::{Data, Json, Path, Query},
+    HttpResponse, Responder,
+};
+use lru_time_cache::LruCache;
+use prover::{guest_input_to_proof_input, ProofEncoding, ProofRequest, ProofResponse, Prover};
+use raiko_core::interfaces::ProofRequest as RpcProofRequest;
+use serde::Serialize;
+use tokio::{
+    sync::{OwnedSemaphorePermit, Semaphore},
+    task::AbortHandle,
+};
+
+use crate::VersionQuery;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobStage {
+    Queued,
+    Fetching,
+    Executing,
+    Signing,
+    Done,
+    Failed,
+    Cancelled,
+}
+
+impl JobStage {
+    pub fn is_finished(&self) -> bool {
+        matches!(self, Self::Done | Self::Failed | Self::Cancelled)
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct JobStatus {
+    pub id: u64,
+    pub stage: JobStage,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    pub elapsed_ms: u128,
+}
+
+struct Job {
+    stage: JobStage,
+    error: Option<String>,
+    result: Option<ProofResponse>,
+    created: Instant,
+    abort: Option<AbortHandle>,
+}
+
+// the jobs are shared by all the http workers. at most `concurrency` jobs run at
+// the same time, the others stay queued. a job is dropped once it hasn't been
+// polled for `ttl`, a running job dropped this way keeps running unobserved.
+//
+// the permit of a job is held by its blocking work, so a job cancelled during the
+// execution still counts until the execution is done.
+#[derive(Clone)]
+pub struct JobManager {
+    jobs: Arc<Mutex<LruCache<u64, Job>>>,
+    next_id: Arc<AtomicU64>,
+    semaphore: Arc<Semaphore>,
+}
+
+enum JobInput {
+    Rpc(RpcProofRequest),
+    Proof(ProofRequest),
+}
+
+impl JobManager {
+    const MAX_JOBS: usize = 4096;
+
+    pub fn new(concurrency: usize, ttl: Duration) -> Self {
+        Self {
+            jobs: Arc::new(Mutex::new(LruCache::with_expiry_duration_and_capacity(
+                ttl,
+                Self::MAX_JOBS,
+            ))),
+            next_id: Arc::new(AtomicU64::new(1)),
+            semaphore: Arc::new(Semaphore::new(concurrency.max(1))),
+        }
+    }
+
+    fn submit(&self, prover: Data<Prover>, input: JobInput, encoding: ProofEncoding) -> u64 {
+        self.submit_with(move |jobs, id, permit| async move {
+            jobs.run(id, prover, input, encoding, permit).await
+        })
+    }
+
+    // the job starts once it gets the permit
+    fn submit_with<F, Fut>(&self, job: F) -> u64
+    where
+        F: FnOnce(JobManager, u64, OwnedSemaphorePermit) -> Fut + 'static,
+        Fut: Future<Output = Result<ProofResponse, String>> + 'static,
+    {
+        let id = self.next_id.fetch_add(1, Ordering::SeqCst);
+        // the job is visible along with its abort handle, so it's never cancelled
+        // without being aborted
+        let mut jobs = self.jobs.lock().unwrap();
+        let handle = spawn({
+            let jobs = self.clone();
+            async move {
+                match jobs.semaphore.clone().acquire_owned().await {
+                    Ok(permit) => job(jobs, id, permit).await,
+                    Err(err) => Err(err.to_string()),
+                }
+            }
+        });
+        let abort = handle.abort_handle();
+        // the job is watched by another task, so a panicked job is failed as well
+        spawn({
+            let jobs = self.clone();
+            async move {
+                let result = match handle.await {
+                    Ok(result) => result,
+                    Err(err) if err.is_panic() => Err(format!("job panicked: {:?}", err)),
+                    Err(err) => Err(err.to_string()),
+                };
+                jobs.finish(id, result);
+            }
+        });
+        jobs.insert(
+            id,
+            Job {
+                stage: JobStage::Queued,
+                error: None,
+                result: None,
+                created: Instant::now(),
+                abort: Some(abort),
+            },
+        );
+        id
+    }
+
+    async fn run(
+        &self,
+        id: u64,
+        prover: Data<Prover>,
+        input: JobInput,
+        encoding: ProofEncoding,
+        permit: OwnedSemaphorePermit,
+    ) -> Result<ProofResponse, String> {
+        let start = Instant::now();
+
+        let req = match input {
+            JobInput::Proof(req) => req,
+            JobInput::Rpc(req) => {
+                self.set_stage(id, JobStage::Fetching);
+                let guest_input = prover
+                    .get_proof(req)
+                    .await
+                    .map_err(|err| format!("fetch input fail: {:?}", err))?;
+                ProofRequest {
+                    input: guest_input_to_proof_input(guest_input)
+                        .map_err(|err| format!("{:?}", err))?,
+                    version: Some(encoding.version()),
+                }
+            }
+        };
+        let fetched = Instant::now();
+        if let Some(response) = prover.cached_proof(&req.input, encoding) {
+            return Ok(response);
+        }
+
+        // execution and signing block the thread. a cancelled job stops observing the
+        // blocking task, which runs to the end of the execution but isn't signed.
+        self.set_stage(id, JobStage::Executing);
+        let response = spawn_blocking({
+            let jobs = self.clone();
+            move || {
+                let _permit = permit;
+                let executed = prover.execute(req).map_err(|err| format!("{:?}", err))?;
+                if !jobs.set_stage(id, JobStage::Signing) {
+                    return Err("job cancelled".to_owned());
+                }
+                prover
+                    .sign(executed, encoding)
+                    .map_err(|err| format!("{:?}", err))
+            }
+        })
+        .await
+        .map_err(|err| format!("{:?}", err))??;
+
+        log::info!(
+            "job[{}] fetch time: {:?}, proving time: {:?}, total: {:?}",
+            id,
+            fetched - start,
+            fetched.elapsed(),
+            start.elapsed()
+        );
+        Ok(response)
+    }
+
+    // false if the job is finished or dropped already
+    fn set_stage(&self, id: u64, stage: JobStage) -> bool {
+        match self.jobs.lock().unwrap().get_mut(&id) {
+            Some(job) if !job.stage.is_finished() => {
+                job.stage = stage;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn finish(&self, id: u64, result: Result<ProofResponse, String>) {
+        let mut jobs = self.jobs.lock().unwrap();
+        let Some(job) = jobs.get_mut(&id) else {
+            return;
+        };
+        if job.stage.is_finished() {
+            return;
+        }
+        job.abort = None;
+        match result {
+            Ok(response) => {
+                job.stage = JobStage::Done;
+                job.result = Some(response);
+            }
+            Err(err) => {
+                log::error!("job[{}] failed: {}", id, err);
+                job.stage = JobStage::Failed;
+                job.error = Some(err);
+            }
+        }
+    }
+
+    pub fn status(&self, id: u64) -> Option<JobStatus> {
+        let mut jobs = self.jobs.lock().unwrap();
+        let job = jobs.get(&id)?;
+        Some(JobStatus {
+            id,
+            stage: job.stage.clone(),
+            error: job.error.clone(),
+            elapsed_ms: job.created.elapsed().as_millis(),
+        })
+    }
+
+    pub fn result(&self, id: u64) -> Option<Result<ProofResponse, JobStatus>> {
+        let status = self.status(id)?;
+        let mut jobs = self.jobs.lock().unwrap();
+        match jobs.get(&id).and_then(|job| job.result.clone()) {
+            Some(response) => Some(Ok(response)),
+            None => Some(Err(status)),
+        }
+    }
+
+    // a finished job can't be cancelled, its status is returned as it is
+    pub fn cancel(&self, id: u64) -> Option<JobStatus> {
+        {
+            let mut jobs = self.jobs.lock().unwrap();
+            let job = jobs.get_mut(&id)?;
+            if !job.stage.is_finished() {
+                job.stage = JobStage::Cancelled;
+                if let Some(abort) = job.abort.take() {
+                    abort.abort();
+                }
+            }
+        }
+        self.status(id)
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct JobSubmitted {
+    id: u64,
+}
+
+#[post("/v1/jobs/get_proof")]
+pub async fn submit_get_proof(
+    jobs: Data<JobManager>,
+    prover: Data<Prover>,
+    req: Json<RpcProofRequest>,
+    query: Query<VersionQuery>,
+) -> impl Responder {
+    let encoding = match ProofEncoding::select(query.version, ProofEncoding::Packed) {
+        Ok(n) => n,
+        Err(err) => return HttpResponse::BadRequest().json(err),
+    };
+    let id = jobs.submit(prover, JobInput::Rpc(req.0), encoding);
+    HttpResponse::Ok().json(JobSubmitted { id })
+}
+
+#[post("/v1/jobs/prove")]
+pub async fn submit_prove(
+    jobs: Data<JobManager>,
+    prover: Data<Prover>,
+    req: Json<ProofRequest>,
+) -> impl Responder {
+    let encoding = match ProofEncoding::select(req.version, ProofEncoding::Packed) {
+        Ok(n) => n,
+        Err(err) => return HttpResponse::BadRequest().json(err),
+    };
+    let id = jobs.submit(prover, JobInput::Proof(req.0), encoding);
+    HttpResponse::Ok().json(JobSubmitted { id })
+}
+
+#[get("/v1/jobs/{id}")]
+pub async fn job_status(jobs: Data<JobManager>, id: Path<u64>) -> impl Responder {
+    match jobs.status(*id) {
+        Some(status) => HttpResponse::Ok().json(status),
+        None => HttpResponse::NotFound().finish(),
+    }
+}
+
+// same as the response of /v1/get_proof, or the status while it's not done
+#[get("/v1/jobs/{id}/result")]
+pub async fn job_result(jobs: Data<JobManager>, id: Path<u64>) -> impl Responder {
+    match jobs.result(*id) {
+        Some(Ok(response)) => HttpResponse::Ok().json(response),
+        Some(Err(status)) => HttpResponse::Conflict().json(status),
+        None => HttpResponse::NotFound().finish(),
+    }
+}
+
+#[post("/v1/jobs/{id}/cancel")]
+pub async fn cancel_job(jobs: Data<JobManager>, id: Path<u64>) -> impl Responder {
+    match jobs.cancel(*id) {
+        Some(status) => HttpResponse::Ok().json(status),
+        None => HttpResponse::NotFound().finish(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::{atomic::AtomicBool, mpsc};
+
+    use actix_web::rt::time::sleep;
+    use tokio::sync::oneshot;
+
+    use super::*;
+
+    fn response() -> ProofResponse {
+        ProofResponse {
+            version: ProofEncoding::Packed.version(),
+            data: Default::default(),
+            signed_poe: None,
+            report: None,
+        }
+    }
+
+    fn stage(jobs: &JobManager, id: u64) -> Option<JobStage> {
+        jobs.status(id).map(|status| status.stage)
+    }
+
+    async fn wait_finished(jobs: &JobManager, id: u64) -> JobStage {
+        for _ in 0..100 {
+            match stage(jobs, id) {
+                Some(stage) if stage.is_finished() => return stage,
+                _ => sleep(Duration::from_millis(10)).await,
+            }
+        }
+        panic!("job[{}] isn't finished: {:?}", id, jobs.status(id));
+    }
+
+    #[actix_web::test]
+    async fn test_job_stages() {
+        let jobs = JobManager::new(1, Duration::from_secs(60));
+        let (tx, rx) = oneshot::channel::<()>();
+        let id = jobs.submit_with(move |jobs, id, _permit| async move {
+            jobs.set_stage(id, JobStage::Fetching);
+            rx.await.map_err(|err| err.to_string())?;
+            jobs.set_stage(id, JobStage::Executing);
+            Ok::<_, String>(response())
+        });
+        assert!(matches!(stage(&jobs, id), Some(JobStage::Queued)));
+        assert!(jobs.result(id).unwrap().is_err());
+
+        sleep(Duration::from_millis(10)).await;
+        assert!(matches!(stage(&jobs, id), Some(JobStage::Fetching)));
+
+        tx.send(()).unwrap();
+        assert!(matches!(wait_finished(&jobs, id).await, JobStage::Done));
+        assert!(jobs.result(id).unwrap().is_ok());
+
+        // a finished job can't be cancelled
+        assert!(matches!(jobs.cancel(id).unwrap().stage, JobStage::Done));
+
+        let id = jobs.submit_with(|_, _, _| async { Err("bad input".to_owned()) });
+        assert!(matches!(wait_finished(&jobs, id).await, JobStage::Failed));
+        assert_eq!(jobs.status(id).unwrap().error.as_deref(), Some("bad input"));
+        assert!(jobs.status(id + 1).is_none());
+    }
+
+    async fn broken_job(
+        _: JobManager,
+        _: u64,
+        _: OwnedSemaphorePermit,
+    ) -> Result<ProofResponse, String> {
+        panic!("broken prover")
+    }
+
+    #[actix_web::test]
+    async fn test_job_panic() {
+        let jobs = JobManager::new(1, Duration::from_secs(60));
+        let id = jobs.submit_with(broken_job);
+        assert!(matches!(wait_finished(&jobs, id).await, JobStage::Failed));
+        let error = jobs.status(id).unwrap().error.unwrap();
+        assert!(error.contains("panicked"), "{}", error);
+
+        // the permit is released by the panicked job
+        let id = jobs.submit_with(|_, _, _| async { Ok(response()) });
+        assert!(matches!(wait_finished(&jobs, id).await, JobStage::Done));
+    }
+
+    #[actix_web::test]
+    async fn test_cancel_before_start() {
+        let jobs = JobManager::new(1, Duration::from_secs(60));
+        let started = Arc::new(AtomicBool::new(false));
+        let id = jobs.submit_with({
+            let started = started.clone();
+            move |_, _, _| async move {
+                started.store(true, Ordering::SeqCst);
+                Ok(response())
+            }
+        });
+        // cancelled right after the submission, before the job gets to run
+        assert!(matches!(
+            jobs.cancel(id).unwrap().stage,
+            JobStage::Cancelled
+        ));
+
+        sleep(Duration::from_millis(50)).await;
+        assert!(!started.load(Ordering::SeqCst));
+        assert!(matches!(stage(&jobs, id), Some(JobStage::Cancelled)));
+        assert!(jobs.result(id).unwrap().is_err());
+    }
+
+    #[actix_web::test]
+    async fn test_cancelled_job_holds_permit() {
+        let jobs = JobManager::new(1, Duration::from_secs(60));
+        let (tx, rx) = mpsc::channel::<()>();
+        let first = jobs.submit_with(move |jobs, id, permit| async move {
+            jobs.set_stage(id, JobStage::Executing);
+            spawn_blocking(move || {
+                let _permit = permit;
+                rx.recv().unwrap();
+            })
+            .await
+            .map_err(|err| err.to_string())?;
+            Ok::<_, String>(response())
+        });
+        let second = jobs.submit_with(|_, _, _| async { Ok(response()) });
+
+        sleep(Duration::from_millis(10)).await;
+        assert!(matches!(stage(&jobs, first), Some(JobStage::Executing)));
+        assert!(matches!(stage(&jobs, second), Some(JobStage::Queued)));
+
+        // the blocking work of the cancelled job is still running
+        jobs.cancel(first);
+        sleep(Duration::from_millis(50)).await;
+        assert!(matches!(stage(&jobs, first), Some(JobStage::Cancelled)));
+        assert!(matches!(stage(&jobs, second), Some(JobStage::Queued)));
+
+        tx.send(()).unwrap();
+        assert!(matches!(wait_finished(&jobs, second).await, JobStage::Done));
+        assert!(matches!(stage(&jobs, first), Some(JobStage::Cancelled)));
+    }
+
+    #[actix_web::test]
+    async fn test_job_ttl() {
+        let jobs = JobManager::new(1, Duration::from_millis(100));
+        let polled = jobs.submit_with(|_, _, _| async { Ok(response()) });
+        let idle = jobs.submit_with(|_, _, _| async { Ok(response()) });
+        assert!(matches!(wait_finished(&jobs, polled).await, JobStage::Done));
+
+        for _ in 0..4 {
+            sleep(Duration::from_millis(50)).await;
+            assert!(jobs.status(polled).is_some());
+        }
+        assert!(jobs.status(idle).is_none());
+        assert!(jobs.result(idle).is_none());
+    }
+}
@@ -6,22 +6,36 @@ use std::{
 use actix_web::{
     post,
     rt::{spawn, time::sleep},
-    web::{Data, Json, JsonConfig},
-    App, HttpResponse, HttpServer, Responder,
+    web::{Bytes, Data, Json, JsonConfig, PayloadConfig, Query},
+    App, HttpMessage, HttpRequest, HttpResponse, HttpServer, Responder,
 };
 use alloy::primitives::{Address, U256};
 use base::{Eth, Keypair, ProverRegistry};
 use clap::Parser;
-use prover::{guest_input_to_proof_input, guest_input_to_proof_inputs, MultiProofRequest, ProofRequest, Prover, RpcMultiProofRequest};
-use prover::{GuestInput, ProverV1ApiServer};
+use jsonrpsee::{server::ServerBuilder, types::ErrorObject};
+use prover::{
+    decode_proof_request, guest_input_to_proof_input, spawn_prover, GenMultiProofRequest,
+    ProofEncoding, ProofRequest, Prover,
+};
+use prover::{GuestInput, PinnedChain, ProofCache, ProverV1ApiServer, TraceRequest};
 use raiko_core::interfaces::ProofRequest as RpcProofRequest;
 use serde::Deserialize;
 use tee::{AttestationReport, ReportBuilder};
 
+mod jobs;
+use jobs::*;
+
 #[post("/debug/gen_proof_by_guest_input")]
 async fn gen_proof_by_guest_input(prover: Data<Prover>, req: Json<GuestInput>) -> impl Responder {
+    let input = match guest_input_to_proof_input(req.0) {
+        Ok(n) => n,
+        Err(err) => {
+            return HttpResponse::BadRequest().json(ErrorObject::owned(14001, err, None::<()>))
+        }
+    };
     let req = ProofRequest {
-        input: guest_input_to_proof_input(req.0).unwrap(),
+        input,
+        version: None,
     };
     match prover.gen_proof(req).await {
         Ok(n) => HttpResponse::Ok().json(n),
@@ -29,31 +43,84 @@ async fn gen_proof_by_guest_input(prover: Data<Prover>, req: Json<GuestInput>) -
     }
 }
 
+#[post("/debug/trace_block")]
+async fn trace_block(prover: Data<Prover>, req: Json<TraceRequest>) -> impl Responder {
+    HttpResponse::Ok().json(prover.trace(req.0))
+}
+
+#[post("/debug/state_diff")]
+async fn state_diff(prover: Data<Prover>, req: Json<ProofRequest>) -> impl Responder {
+    match prover.state_diff(req.0) {
+        Ok(n) => HttpResponse::Ok().json(n),
+        Err(err) => HttpResponse::BadRequest().json(err),
+    }
+}
+
+// the body is json or bincode by the Content-Type, and may be compressed by the
+// Content-Encoding (gzip, deflate, zstd, br) which is decoded by actix
 #[post("/v1/gen_proof")]
-async fn gen_proof(prover: Data<Prover>, req: Json<ProofRequest>) -> impl Responder {
-    match prover.gen_proof(req.0).await {
+async fn gen_proof(prover: Data<Prover>, http_req: HttpRequest, body: Bytes) -> impl Responder {
+    let req = match decode_proof_request(http_req.content_type(), &body) {
+        Ok(n) => n,
+        Err(err) => {
+            let err = ErrorObject::owned(14001, format!("{:?}", err), None::<()>);
+            return HttpResponse::BadRequest().json(err);
+        }
+    };
+    match prover.gen_proof(req).await {
         Ok(n) => HttpResponse::Ok().json(n),
         Err(err) => HttpResponse::BadRequest().json(err),
     }
 }
 
+// the encoding of the response, see `ProofEncoding`
+#[derive(Debug, Deserialize)]
+struct VersionQuery {
+    version: Option<u64>,
+}
+
 #[post("/v1/get_proof")]
-async fn get_proof(prover: Data<Prover>, req: Json<RpcProofRequest>) -> impl Responder {
+async fn get_proof(
+    prover: Data<Prover>,
+    req: Json<RpcProofRequest>,
+    query: Query<VersionQuery>,
+) -> impl Responder {
+    let encoding = match ProofEncoding::select(query.version, ProofEncoding::Packed) {
+        Ok(n) => n,
+        Err(err) => return HttpResponse::BadRequest().json(err),
+    };
     let req_data = serde_json::to_string(&req.0);
     let block_number = req.block_number;
     log::info!("req: {:?}", req_data);
 
     let start = Instant::now();
 
-    let guest_input = prover.get_proof(req.0).await;
+    let guest_input = match prover.get_proof(req.0).await {
+        Ok(n) => n,
+        Err(err) => {
+            log::error!("err: {:?}", err);
+            return HttpResponse::BadRequest().json(err);
+        }
+    };
 
     let gen_proof_instant = Instant::now();
 
+    let input = match guest_input_to_proof_input(guest_input) {
+        Ok(n) => n,
+        Err(err) => {
+            log::error!("err: {:?}", err);
+            return HttpResponse::BadRequest().json(ErrorObject::owned(14001, err, None::<()>));
+        }
+    };
     let proof_request = ProofRequest {
-        input: guest_input_to_proof_input(guest_input).unwrap(),
+        input,
+        version: query.version,
     };
 
-    let result = match prover.prove(proof_request) {
+    let proved = spawn_prover(&prover, move |prover| {
+        prover.prove_encoded(proof_request, encoding)
+    });
+    let result = match proved.await {
         Ok(n) => HttpResponse::Ok().json(n),
         Err(err) => {
             log::error!("err: {:?}", err);
@@ -61,26 +128,37 @@ async fn get_proof(prover: Data<Prover>, req: Json<RpcProofRequest>) -> impl Res
         }
     };
 
-    log::info!("gen proof time: {:?}, proving time: {:?}, total: {:?}", gen_proof_instant-start, gen_proof_instant.elapsed(), start.elapsed());
+    log::info!(
+        "gen proof time: {:?}, proving time: {:?}, total: {:?}",
+        gen_proof_instant - start,
+        gen_proof_instant.elapsed(),
+        start.elapsed()
+    );
     result
 }
 
+// one proof for contiguous blocks, by their inputs or the block range to fetch.
+// the response is packed by default the same as /v1/get_proof, see `MultiProofRequest`
+// for what the aggregated poe covers.
 #[post("/v1/get_proofs")]
-async fn get_proofs(prover: Data<Prover>, req: Json<RpcMultiProofRequest>) -> impl Responder {
+async fn get_proofs(prover: Data<Prover>, req: Json<GenMultiProofRequest>) -> impl Responder {
     let start = Instant::now();
 
-    let guest_inputs = match prover.get_proofs(req.0).await {
+    let encoding = match ProofEncoding::select(req.version(), ProofEncoding::Packed) {
+        Ok(n) => n,
+        Err(err) => return HttpResponse::BadRequest().json(err),
+    };
+    let input = match prover.multi_proof_inputs(req.0).await {
         Ok(n) => n,
         Err(err) => return HttpResponse::BadRequest().json(err),
     };
 
     let gen_proof_instant = Instant::now();
 
-    let proof_request = MultiProofRequest {
-        input: guest_input_to_proof_inputs(guest_inputs).unwrap(),
-    };
-
-    let result = match prover.prove_multi(proof_request).await {
+    let proved = spawn_prover(&prover, move |prover| {
+        prover.prove_multi_encoded(input, encoding)
+    });
+    let result = match proved.await {
         Ok(n) => HttpResponse::Ok().json(n),
         Err(err) => {
             log::error!("err: {:?}", err);
@@ -88,7 +166,12 @@ async fn get_proofs(prover: Data<Prover>, req: Json<RpcMultiProofRequest>) -> im
         }
     };
 
-    log::info!("gen proof time: {:?}, proving time: {:?}, total: {:?}", gen_proof_instant-start, gen_proof_instant.elapsed(), start.elapsed());
+    log::info!(
+        "gen proof time: {:?}, proving time: {:?}, total: {:?}",
+        gen_proof_instant - start,
+        gen_proof_instant.elapsed(),
+        start.elapsed()
+    );
     result
 }
 
@@ -109,10 +192,51 @@ pub struct MultiProver {
     pub prover_registry: Address,
     #[clap(long, env = "LISTEN", default_value = "127.0.0.1:20300")]
     pub listen: String,
+    // the json-rpc server of ProverV1Api over http and websocket, disabled if empty
+    #[clap(long, env = "RPC_LISTEN", default_value = "127.0.0.1:20301")]
+    #[serde(default)]
+    pub rpc_listen: String,
     #[clap(long, default_value = "1800")]
     pub attestation_pre_expire_secs: u64,
     #[clap(long, default_value = "8")]
     pub worker_num: usize,
+    // json list of the chain specs to register besides the builtin ones
+    #[clap(long, env = "CHAIN_CONFIG", default_value = "")]
+    #[serde(default)]
+    pub chain_config: String,
+    // execute every block with raiko as well and refuse to sign on divergence,
+    // the requests of several blocks are rejected as raiko can't execute them
+    #[clap(long, env = "CROSS_CHECK")]
+    #[serde(default)]
+    pub cross_check: bool,
+    // the chains and contracts allowed to sign for, only from the config file
+    #[clap(skip)]
+    #[serde(default)]
+    pub pinned_chains: Vec<PinnedChain>,
+    // sign for every chain if no chain is pinned, otherwise nothing is signed
+    #[clap(long, env = "ALLOW_UNPINNED")]
+    #[serde(default)]
+    pub allow_unpinned: bool,
+    // sign the taiko blocks requested without their tx list, their txs are unchecked
+    #[clap(long, env = "ALLOW_MISSING_TX_LIST")]
+    #[serde(default)]
+    pub allow_missing_tx_list: bool,
+    // the number of jobs proving at the same time, the others are queued
+    #[clap(long, env = "MAX_RUNNING_JOBS", default_value = "2")]
+    #[serde(default)]
+    pub max_running_jobs: usize,
+    // a job is dropped if it isn't polled within this time
+    #[clap(long, env = "JOB_TTL_SECS", default_value = "3600")]
+    #[serde(default)]
+    pub job_ttl_secs: u64,
+    // the most blocks of a range to prove together, larger ranges are rejected
+    #[clap(long, env = "MAX_PROOFS_RANGE", default_value = "32")]
+    #[serde(default)]
+    pub max_proofs_range: u64,
+    // sqlite file of the signed proofs, retries of the proved blocks are served from it
+    #[clap(long, env = "PROOF_CACHE", default_value = "")]
+    #[serde(default)]
+    pub proof_cache: String,
 }
 
 impl MultiProver {
@@ -129,9 +253,33 @@ impl MultiProver {
         if self.listen == "127.0.0.1:20300" && rhs.listen != "" {
             self.listen = rhs.listen
         }
+        if self.rpc_listen == "127.0.0.1:20301" && rhs.rpc_listen != "" {
+            self.rpc_listen = rhs.rpc_listen
+        }
         if self.attestation_pre_expire_secs == 1800 && rhs.attestation_pre_expire_secs > 0 {
             self.attestation_pre_expire_secs = rhs.attestation_pre_expire_secs
         }
+        if self.chain_config == "" {
+            self.chain_config = rhs.chain_config;
+        }
+        self.cross_check |= rhs.cross_check;
+        if self.pinned_chains.is_empty() {
+            self.pinned_chains = rhs.pinned_chains;
+        }
+        self.allow_unpinned |= rhs.allow_unpinned;
+        self.allow_missing_tx_list |= rhs.allow_missing_tx_list;
+        if self.max_running_jobs == 2 && rhs.max_running_jobs > 0 {
+            self.max_running_jobs = rhs.max_running_jobs;
+        }
+        if self.job_ttl_secs == 3600 && rhs.job_ttl_secs > 0 {
+            self.job_ttl_secs = rhs.job_ttl_secs;
+        }
+        if self.max_proofs_range == 32 && rhs.max_proofs_range > 0 {
+            self.max_proofs_range = rhs.max_proofs_range;
+        }
+        if self.proof_cache == "" {
+            self.proof_cache = rhs.proof_cache;
+        }
     }
 }
 
@@ -146,6 +294,18 @@ async fn main() -> std::io::Result<()> {
         let data = std::fs::read(&mp.config).unwrap();
         mp.merge(serde_json::from_slice(&data).unwrap());
     }
+    if mp.chain_config != "" {
+        let chain_ids = executor::register_chains_from_file(&mp.chain_config).unwrap();
+        log::info!("registered chains: {:?}", chain_ids);
+    }
+    if mp.pinned_chains.is_empty() {
+        match mp.allow_unpinned {
+            true => log::warn!("no pinned chains, the requests of any chain will be signed"),
+            false => {
+                log::error!("no pinned chains, nothing will be signed without --allow-unpinned")
+            }
+        }
+    }
 
     let kp = Keypair::new();
 
@@ -184,15 +344,62 @@ async fn main() -> std::io::Result<()> {
         mp.attestation_pre_expire_secs,
     ));
 
+    let proof_cache = match mp.proof_cache.as_str() {
+        "" => None,
+        path => Some(ProofCache::open(path).unwrap()),
+    };
+    let jobs = JobManager::new(mp.max_running_jobs, Duration::from_secs(mp.job_ttl_secs));
+
+    let new_prover = {
+        let (prover_registry, worker_num) = (mp.prover_registry, mp.worker_num);
+        let (cross_check, pinned_chains) = (mp.cross_check, mp.pinned_chains.clone());
+        let (allow_unpinned, max_proofs_range) = (mp.allow_unpinned, mp.max_proofs_range);
+        let allow_missing_tx_list = mp.allow_missing_tx_list;
+        move || {
+            let mut prover = Prover::new(kp.clone(), prover_registry, tee_type, worker_num)
+                .cross_check(cross_check)
+                .pinned_chains(pinned_chains.clone())
+                .allow_unpinned(allow_unpinned)
+                .allow_missing_tx_list(allow_missing_tx_list)
+                .max_proofs_range(max_proofs_range);
+            if let Some(cache) = &proof_cache {
+                prover = prover.cache(cache.clone());
+            }
+            prover
+        }
+    };
+
+    let _rpc_handle = match mp.rpc_listen.as_str() {
+        "" => None,
+        addr => {
+            let server = ServerBuilder::default()
+                .max_request_body_size(100 << 20)
+                .build(addr)
+                .await?;
+            log::info!("json-rpc server listening on {:?}", server.local_addr()?);
+            Some(server.start(new_prover().into_rpc()))
+        }
+    };
+
     HttpServer::new(move || {
-        let prover = Prover::new(kp.clone(), mp.prover_registry, tee_type, mp.worker_num);
+        let prover = new_prover();
 
         App::new()
             .app_data(JsonConfig::default().limit(100 << 20))
+            .app_data(PayloadConfig::new(100 << 20))
             .app_data(Data::new(prover))
+            .app_data(Data::new(jobs.clone()))
             .service(gen_proof)
             .service(gen_proof_by_guest_input)
             .service(get_proof)
+            .service(get_proofs)
+            .service(state_diff)
+            .service(trace_block)
+            .service(submit_get_proof)
+            .service(submit_prove)
+            .service(job_status)
+            .service(job_result)
+            .service(cancel_job)
     })
     .bind(mp.listen)?
     .run()
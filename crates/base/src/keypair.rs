@@ -1,7 +1,11 @@
 use std::sync::{Arc, Mutex};
 
 use alloy::primitives::{keccak256, Address, U256};
-use secp256k1::{rand::thread_rng, Message, PublicKey, SECP256K1};
+use secp256k1::{
+    ecdsa::{RecoverableSignature, RecoveryId},
+    rand::thread_rng,
+    Message, PublicKey, SECP256K1,
+};
 
 pub use secp256k1::SecretKey;
 
@@ -61,6 +65,17 @@ impl Keypair {
         sig[64] = v.to_i32() as u8 + 27;
         sig
     }
+
+    // the signer of `sign_digest_ecdsa`, v is 27 or 28 as required by the contracts
+    pub fn recover_digest_ecdsa(digest: [u8; 32], sig: &[u8; 65]) -> Option<Address> {
+        let v = sig[64].checked_sub(27).filter(|v| *v <= 1)?;
+        let id = RecoveryId::from_i32(v as i32).ok()?;
+        let sig = RecoverableSignature::from_compact(&sig[..64], id).ok()?;
+        let pk = SECP256K1
+            .recover_ecdsa(&Message::from_digest(digest), &sig)
+            .ok()?;
+        Some(Self::public_key_to_address(&pk))
+    }
 }
 
 pub struct KeypairRotate<'a> {
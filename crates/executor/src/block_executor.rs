@@ -1,30 +1,43 @@
-use std::{collections::HashMap, sync::Arc};
+use std::{collections::HashMap, sync::Arc, time::Instant};
 
 use raiko_lib::primitives::mpt::MptNode;
 use reth_chainspec::ChainSpec;
 use reth_evm::{
     execute::{BlockValidationError, Executor, ProviderError},
-    BundleState,
+    BundleState, ConfigureEvm,
 };
-use reth_evm_ethereum::{execute::EthExecutorProvider, taiko::TaikoData};
+use reth_evm_ethereum::{execute::EthExecutorProvider, taiko::TaikoData, EthEvmConfig};
 use reth_primitives::{
-    revm_primitives::{Account, AccountStatus},
-    Address, Block, BlockWithSenders, Bytes, B256, U256,
+    proofs::{calculate_receipt_root, calculate_transaction_root, calculate_withdrawals_root},
+    revm_primitives::Account,
+    Address, Block, BlockWithSenders, Bloom, Bytes, Receipt, B256, U256,
 };
 
 use crate::{
-    DataProviderError, DataProviderResult, ExecutionError, ExecutionResult, MemDB, CHAIN_LIST,
+    account_change, get_chain_spec, registered_chain_ids, AccessedState, BlockReport,
+    DataProviderError, DataProviderResult, ExecutionError, ExecutionReport, ExecutionResult, MemDB,
+    StateDiff, Tracer, TracingEvmConfig,
 };
 
 pub trait BlockDataProvider {
     type ExtData;
 
     fn chain_id(&self) -> u64;
-    fn block(&self) -> &Block;
+    // the consecutive blocks to execute, all of them share the same witness
+    fn blocks(&self) -> &[Block];
     fn contract_codes(&self) -> &[Bytes];
-    fn block_hash(&self, number: u64) -> B256;
-    fn state_trie(&self) -> &MptNode;
-    fn storage_state_trie(&self, addr: Address) -> &MptNode;
+    // the code which is not in `contract_codes`, for the providers fetching it on demand
+    fn code_by_hash(&self, _code_hash: B256) -> Option<Bytes> {
+        None
+    }
+    fn block_hash(&self, number: u64) -> Result<B256, ProviderError>;
+    // whether the tries are there before the execution, the providers fetching the
+    // witness on demand only build them once the changes are applied
+    fn has_witness(&self) -> bool {
+        true
+    }
+    fn state_trie(&self) -> DataProviderResult<&MptNode>;
+    fn storage_state_trie(&self, addr: Address) -> Option<&MptNode>;
 
     fn get_acc<T: alloy_rlp::Decodable>(&self, key: Address) -> Result<Option<T>, ProviderError>;
     fn get_slot<T: alloy_rlp::Decodable>(
@@ -34,16 +47,21 @@ pub trait BlockDataProvider {
         slot: U256,
     ) -> Result<Option<T>, ProviderError>;
 
-    fn ext_data(&self) -> Self::ExtData;
+    // the ext data for the block at `idx` of `blocks()`
+    fn ext_data(&self, idx: usize) -> Self::ExtData;
+
+    // make sure the witness covers everything the blocks require,
+    // reports all the missing parts at once instead of failing on the first one.
+    fn check_witness(&self, blocks: &[BlockWithSenders]) -> DataProviderResult<()>;
 
     fn get_chain_spec(&self) -> DataProviderResult<Arc<ChainSpec>> {
         let chain_id = self.chain_id();
-        if let Some(spec) = CHAIN_LIST.get(&chain_id) {
-            Ok(spec.clone())
+        if let Some(spec) = get_chain_spec(chain_id) {
+            Ok(spec)
         } else {
             Err(DataProviderError::UnsupportChainId(
                 chain_id,
-                CHAIN_LIST.keys().cloned().collect(),
+                registered_chain_ids(),
             ))
         }
     }
@@ -51,6 +69,8 @@ pub trait BlockDataProvider {
 
 pub struct BlockExecutor<P: BlockDataProvider> {
     provider: Arc<P>,
+    cache: bool,
+    parallel: bool,
 }
 
 impl<P> BlockExecutor<P>
@@ -58,49 +78,168 @@ where
     P: BlockDataProvider<ExtData = TaikoData>,
 {
     pub fn new(provider: Arc<P>) -> Self {
-        Self { provider }
+        Self {
+            provider,
+            cache: true,
+            parallel: true,
+        }
+    }
+
+    // update the storage tries in parallel after each block, enabled by default
+    pub fn parallel(mut self, enabled: bool) -> Self {
+        self.parallel = enabled;
+        self
+    }
+
+    // cache the decoded accounts and slots in MemDB, enabled by default
+    pub fn cache(mut self, enabled: bool) -> Self {
+        self.cache = enabled;
+        self
     }
 
     fn collect_changes(&self, state: BundleState) -> HashMap<Address, Account> {
         state
             .state
             .into_iter()
-            .map(|(address, bundle_account)| {
-                let mut account = Account {
-                    info: bundle_account.info.unwrap_or_default(),
-                    storage: bundle_account.storage,
-                    status: AccountStatus::default(),
-                };
-                account.mark_touch();
-                if bundle_account.status.was_destroyed() {
-                    account.mark_selfdestruct();
-                }
-                if bundle_account.original_info.is_none() {
-                    account.mark_created();
-                }
-                (address, account)
-            })
+            .map(|(address, bundle_account)| (address, account_change(bundle_account)))
             .collect()
     }
 
+    // execute all the blocks and return the last one
     pub fn execute(&self) -> ExecutionResult<BlockWithSenders> {
+        let mut blocks = self.execute_blocks()?;
+        blocks.pop().ok_or(ExecutionError::EmptyBlocks)
+    }
+
+    // execute all the blocks and return the last one,
+    // along with the state changes made by all of the blocks
+    pub fn execute_with_diff(&self) -> ExecutionResult<(BlockWithSenders, StateDiff)> {
+        let mut diff = StateDiff::default();
+        let (mut blocks, _, _) = self.execute_blocks_inner(Some(&mut diff), None)?;
+        let block = blocks.pop().ok_or(ExecutionError::EmptyBlocks)?;
+        Ok((block, diff))
+    }
+
+    // execute all the blocks with the tracer attached and return the last one,
+    // the tracer keeps what it collected even if the execution fails.
+    pub fn execute_with_tracer(&self, tracer: &Tracer) -> ExecutionResult<BlockWithSenders> {
+        let (mut blocks, _, _) = self.execute_blocks_inner(None, Some(tracer))?;
+        blocks.pop().ok_or(ExecutionError::EmptyBlocks)
+    }
+
+    // execute all the blocks and return the last one,
+    // along with the part of the witness that has been accessed
+    pub fn execute_with_access(&self) -> ExecutionResult<(BlockWithSenders, AccessedState)> {
+        let (mut blocks, db, _) = self.execute_blocks_inner(None, None)?;
+        let block = blocks.pop().ok_or(ExecutionError::EmptyBlocks)?;
+        Ok((block, db.accessed().clone()))
+    }
+
+    // execute all the blocks and return the last one,
+    // along with the metrics of every block
+    pub fn execute_with_report(&self) -> ExecutionResult<(BlockWithSenders, ExecutionReport)> {
+        let (mut blocks, _, report) = self.execute_blocks_inner(None, None)?;
+        let block = blocks.pop().ok_or(ExecutionError::EmptyBlocks)?;
+        Ok((block, report))
+    }
+
+    // execute the blocks in order, the post state of a block is the pre state of the next one
+    pub fn execute_blocks(&self) -> ExecutionResult<Vec<BlockWithSenders>> {
+        let (blocks, _, _) = self.execute_blocks_inner(None, None)?;
+        Ok(blocks)
+    }
+
+    fn execute_blocks_inner(
+        &self,
+        mut diff: Option<&mut StateDiff>,
+        tracer: Option<&Tracer>,
+    ) -> ExecutionResult<(Vec<BlockWithSenders>, MemDB<P>, ExecutionReport)> {
         let chain_spec = self.provider.get_chain_spec()?;
-        let db = MemDB::new(self.provider.clone());
-        let executor = EthExecutorProvider::ethereum(chain_spec)
-            .eth_executor(db)
-            .taiko_data(self.provider.ext_data())
-            .optimistic(false);
 
-        let block = self.provider.block().clone();
+        let mut blocks = Vec::with_capacity(self.provider.blocks().len());
+        let mut sender_recovery = Vec::with_capacity(blocks.capacity());
+        for block in self.provider.blocks() {
+            let start = Instant::now();
+            let block = block
+                .clone()
+                .with_recovered_senders()
+                .ok_or(BlockValidationError::SenderRecoveryError)?;
+            sender_recovery.push(start.elapsed());
+            blocks.push(block);
+        }
+        if blocks.is_empty() {
+            return Err(ExecutionError::EmptyBlocks);
+        }
+
+        self.provider.check_witness(&blocks)?;
+
+        let mut db = MemDB::new(self.provider.clone())
+            .with_cache(self.cache)
+            .with_parallel(self.parallel);
+        let mut report = ExecutionReport::default();
+        for (idx, block) in blocks.iter().enumerate() {
+            let chain_spec = chain_spec.clone();
+            let diff = diff.as_deref_mut();
+            let result = match tracer {
+                Some(tracer) => {
+                    tracer.start_block(block);
+                    let evm_config = TracingEvmConfig::new(tracer.clone());
+                    self.execute_block(chain_spec, evm_config, db, idx, block, diff)
+                }
+                None => {
+                    let evm_config = EthEvmConfig::default();
+                    self.execute_block(chain_spec, evm_config, db, idx, block, diff)
+                }
+            };
+            let (next_db, mut block_report) =
+                result.map_err(ExecutionError::Block(&block.number))?;
+            db = next_db;
+            block_report.sender_recovery_us = sender_recovery[idx].as_micros() as u64;
+            report.blocks.push(block_report);
+        }
+
+        Ok((blocks, db, report))
+    }
+
+    fn execute_block<E: ConfigureEvm>(
+        &self,
+        chain_spec: Arc<ChainSpec>,
+        evm_config: E,
+        db: MemDB<P>,
+        idx: usize,
+        block: &BlockWithSenders,
+        diff: Option<&mut StateDiff>,
+    ) -> ExecutionResult<(MemDB<P>, BlockReport)> {
+        let mut report = BlockReport {
+            number: block.number,
+            tx_count: block.body.len(),
+            ..Default::default()
+        };
+        let taiko_data = self.provider.ext_data(idx);
+        let parent_hash = taiko_data.parent_header.hash_slow();
+        if block.header.parent_hash != parent_hash {
+            return Err(ExecutionError::ParentHashMismatch {
+                remote: block.header.parent_hash,
+                local: parent_hash,
+            });
+        }
+        self.verify_body(block)?;
 
-        let block = block
-            .with_recovered_senders()
-            .ok_or(BlockValidationError::SenderRecoveryError)?;
+        let executor = EthExecutorProvider::new(chain_spec, evm_config)
+            .eth_executor(db)
+            .taiko_data(taiko_data)
+            .optimistic(false);
 
-        let input = (&block, U256::ZERO).into();
+        let input = (block, U256::ZERO).into();
+        let start = Instant::now();
         let result = executor
             .execute(input)
             .map_err(ExecutionError::ExecuteBlock())?;
+        report.execution_us = start.elapsed().as_micros() as u64;
+        report.gas_used = result.gas_used;
+        if let Some(diff) = diff {
+            diff.merge(StateDiff::from_bundle(&result.state));
+        }
         let changes = self.collect_changes(result.state);
 
         // make sure all txs are executed
@@ -111,20 +250,91 @@ where
                 local: result.valid_transaction_indices,
             });
         }
+        self.verify_receipts(block, result.gas_used, result.receipts)?;
 
-        let new_state_trie = result
-            .db
-            .database
+        let mut db = result.db.database;
+        let start = Instant::now();
+        let state_root = db
             .apply_changes(changes)
             .map_err(ExecutionError::ApplyChanges())?;
+        report.trie_update_us = start.elapsed().as_micros() as u64;
 
-        if block.header.state_root != new_state_trie.hash() {
+        let metrics = db.take_metrics();
+        report.accounts_touched = metrics.accounts_touched;
+        report.slots_touched = metrics.slots_touched;
+        report.trie_nodes_read = metrics.trie_nodes_read;
+        report.code_bytes_loaded = metrics.code_bytes_loaded;
+
+        if block.header.state_root != state_root {
             return Err(ExecutionError::StateRootMismatch {
                 remote: block.header.state_root,
-                local: new_state_trie.hash(),
+                local: state_root,
+            });
+        }
+        db.insert_block_hash(block.header.number, block.header.hash_slow());
+
+        Ok((db, report))
+    }
+
+    // the header fields which only depend on the block body
+    fn verify_body(&self, block: &BlockWithSenders) -> ExecutionResult<()> {
+        let transactions_root = calculate_transaction_root(&block.body);
+        if block.header.transactions_root != transactions_root {
+            return Err(ExecutionError::TransactionsRootMismatch {
+                remote: block.header.transactions_root,
+                local: transactions_root,
             });
         }
 
-        Ok(block)
+        let withdrawals_root = block
+            .withdrawals
+            .as_ref()
+            .map(|withdrawals| calculate_withdrawals_root(withdrawals));
+        if block.header.withdrawals_root != withdrawals_root {
+            return Err(ExecutionError::WithdrawalsRootMismatch {
+                remote: block.header.withdrawals_root,
+                local: withdrawals_root,
+            });
+        }
+        Ok(())
+    }
+
+    // the header fields which depend on the execution result
+    fn verify_receipts(
+        &self,
+        block: &BlockWithSenders,
+        gas_used: u64,
+        receipts: Vec<Receipt>,
+    ) -> ExecutionResult<()> {
+        if block.header.gas_used != gas_used {
+            return Err(ExecutionError::GasUsedMismatch {
+                remote: block.header.gas_used,
+                local: gas_used,
+            });
+        }
+
+        let receipts = receipts
+            .into_iter()
+            .map(|receipt| receipt.with_bloom())
+            .collect::<Vec<_>>();
+
+        let receipts_root = calculate_receipt_root(&receipts);
+        if block.header.receipts_root != receipts_root {
+            return Err(ExecutionError::ReceiptsRootMismatch {
+                remote: block.header.receipts_root,
+                local: receipts_root,
+            });
+        }
+
+        let logs_bloom = receipts
+            .iter()
+            .fold(Bloom::ZERO, |bloom, receipt| bloom | receipt.bloom);
+        if block.header.logs_bloom != logs_bloom {
+            return Err(ExecutionError::LogsBloomMismatch {
+                remote: block.header.logs_bloom,
+                local: logs_bloom,
+            });
+        }
+        Ok(())
     }
 }
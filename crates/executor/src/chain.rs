@@ -1,10 +1,90 @@
 use reth_chainspec::{
     ChainSpec, ChainSpecBuilder, HOLESKY, MAINNET, TAIKO_A7, TAIKO_DEV, TAIKO_MAINNET, UNIFI_TESTNET
 };
-use std::{collections::BTreeMap, sync::Arc};
+use reth_primitives::{Address, ForkCondition, Genesis, Hardfork};
+use serde::Deserialize;
+use std::{
+    collections::BTreeMap,
+    str::FromStr,
+    sync::{Arc, RwLock},
+};
+
+use crate::ChainConfigError;
 
 lazy_static::lazy_static! {
-    pub static ref CHAIN_LIST: BTreeMap<u64, Arc<ChainSpec>> = chain_map(available_chain_list());
+    static ref CHAIN_REGISTRY: RwLock<BTreeMap<u64, ChainEntry>> = RwLock::new(chain_map(available_chain_list()));
+}
+
+#[derive(Clone)]
+struct ChainEntry {
+    spec: Arc<ChainSpec>,
+    contracts: TaikoContracts,
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+pub struct TaikoContracts {
+    pub l1_contract: Option<Address>,
+    pub l2_contract: Option<Address>,
+}
+
+// the chain spec described by the config file:
+// {
+//     "chain_id": 167100,
+//     "base_chain_id": 167009,
+//     "hardforks": { "hekla": { "block": 0 }, "ontake": { "block": 20 } },
+//     "l1_contract": "0x...",
+//     "l2_contract": "0x..."
+// }
+#[derive(Debug, Clone, Deserialize)]
+pub struct ChainConfig {
+    pub chain_id: u64,
+    // start from the spec of a registered chain, the genesis and hardforks are inherited
+    #[serde(default)]
+    pub base_chain_id: Option<u64>,
+    #[serde(default)]
+    pub genesis: Option<Genesis>,
+    #[serde(default)]
+    pub hardforks: BTreeMap<String, ForkActivation>,
+    #[serde(flatten)]
+    pub contracts: TaikoContracts,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ForkActivation {
+    Block(u64),
+    Timestamp(u64),
+}
+
+impl From<ForkActivation> for ForkCondition {
+    fn from(value: ForkActivation) -> Self {
+        match value {
+            ForkActivation::Block(number) => ForkCondition::Block(number),
+            ForkActivation::Timestamp(ts) => ForkCondition::Timestamp(ts),
+        }
+    }
+}
+
+impl ChainConfig {
+    pub fn build(&self) -> Result<ChainSpec, ChainConfigError> {
+        let mut builder = match self.base_chain_id {
+            Some(base) => {
+                let spec = get_chain_spec(base).ok_or(ChainConfigError::UnknownBaseChain(base))?;
+                ChainSpecBuilder::from(&spec)
+            }
+            None => ChainSpecBuilder::default(),
+        };
+        builder = builder.chain(self.chain_id.into());
+        if let Some(genesis) = &self.genesis {
+            builder = builder.genesis(genesis.clone());
+        }
+        for (name, activation) in &self.hardforks {
+            let fork = Hardfork::from_str(name)
+                .map_err(|_| ChainConfigError::UnknownHardfork(name.clone()))?;
+            builder = builder.with_fork(fork, (*activation).into());
+        }
+        Ok(builder.build())
+    }
 }
 
 fn available_chain_list() -> Vec<Arc<ChainSpec>> {
@@ -24,10 +104,153 @@ fn available_chain_list() -> Vec<Arc<ChainSpec>> {
     ]
 }
 
-fn chain_map(list: Vec<Arc<ChainSpec>>) -> BTreeMap<u64, Arc<ChainSpec>> {
+fn chain_map(list: Vec<Arc<ChainSpec>>) -> BTreeMap<u64, ChainEntry> {
     let mut chain_list = BTreeMap::new();
     for chain in list {
-        chain_list.insert(chain.chain().id(), chain.clone());
+        chain_list.insert(
+            chain.chain().id(),
+            ChainEntry {
+                spec: chain.clone(),
+                contracts: TaikoContracts::default(),
+            },
+        );
     }
     chain_list
 }
+
+pub fn get_chain_spec(chain_id: u64) -> Option<Arc<ChainSpec>> {
+    let registry = CHAIN_REGISTRY.read().unwrap();
+    registry.get(&chain_id).map(|n| n.spec.clone())
+}
+
+pub fn get_chain_contracts(chain_id: u64) -> Option<TaikoContracts> {
+    let registry = CHAIN_REGISTRY.read().unwrap();
+    registry.get(&chain_id).map(|n| n.contracts)
+}
+
+pub fn registered_chain_ids() -> Vec<u64> {
+    CHAIN_REGISTRY.read().unwrap().keys().cloned().collect()
+}
+
+// register or replace the chain spec with the same chain id
+pub fn register_chain_spec(spec: Arc<ChainSpec>, contracts: TaikoContracts) {
+    let mut registry = CHAIN_REGISTRY.write().unwrap();
+    registry.insert(spec.chain().id(), ChainEntry { spec, contracts });
+}
+
+// register the chains described by a json list of `ChainConfig`,
+// the chains are registered in order so a chain can be based on a previous one.
+pub fn register_chains_from_file(path: &str) -> Result<Vec<u64>, ChainConfigError> {
+    let data = std::fs::read(path).map_err(ChainConfigError::ReadConfig(&path.to_owned()))?;
+    let configs: Vec<ChainConfig> =
+        serde_json::from_slice(&data).map_err(ChainConfigError::ReadConfig(&path.to_owned()))?;
+
+    // a chain listed twice is likely a mistake, reject the file before registering anything
+    let mut chain_ids = Vec::with_capacity(configs.len());
+    for config in &configs {
+        if chain_ids.contains(&config.chain_id) {
+            return Err(ChainConfigError::DuplicateChain(config.chain_id));
+        }
+        chain_ids.push(config.chain_id);
+    }
+
+    for config in configs {
+        let spec = config
+            .build()
+            .map_err(ChainConfigError::Chain(&config.chain_id))?;
+        register_chain_spec(Arc::new(spec), config.contracts);
+    }
+    Ok(chain_ids)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // the registry is global, every test uses its own chain ids
+    fn register(name: &str, configs: serde_json::Value) -> Result<Vec<u64>, ChainConfigError> {
+        let path =
+            std::env::temp_dir().join(format!("chain-config-{}-{}.json", name, std::process::id()));
+        std::fs::write(&path, serde_json::to_vec(&configs).unwrap()).unwrap();
+        let result = register_chains_from_file(path.to_str().unwrap());
+        std::fs::remove_file(&path).unwrap();
+        result
+    }
+
+    #[test]
+    fn test_register_chains_from_file() {
+        let base = TAIKO_A7.clone();
+        let chain_ids = register(
+            "round-trip",
+            serde_json::json!([
+                { "chain_id": 9_000_001, "base_chain_id": base.chain().id() },
+                {
+                    "chain_id": 9_000_002,
+                    "base_chain_id": 9_000_001,
+                    "hardforks": { "ontake": { "block": 20 } },
+                    "l2_contract": "0x9a7fe8e9852c684dd03ce9caea003f5fc0a3135b"
+                }
+            ]),
+        )
+        .unwrap();
+        assert_eq!(chain_ids, vec![9_000_001, 9_000_002]);
+
+        let spec = get_chain_spec(9_000_001).unwrap();
+        assert_eq!(spec.chain().id(), 9_000_001);
+        assert_eq!(spec.genesis_hash(), base.genesis_hash());
+        assert_eq!(spec.hardforks, base.hardforks);
+
+        // the forks which are not listed are inherited
+        let spec = get_chain_spec(9_000_002).unwrap();
+        assert_eq!(spec.genesis_hash(), base.genesis_hash());
+        assert_eq!(spec.fork(Hardfork::Ontake), ForkCondition::Block(20));
+        for (fork, condition) in &base.hardforks {
+            if *fork != Hardfork::Ontake {
+                assert_eq!(spec.fork(*fork), *condition);
+            }
+        }
+        let contracts = get_chain_contracts(9_000_002).unwrap();
+        assert_eq!(contracts.l1_contract, None);
+        assert_eq!(
+            contracts.l2_contract,
+            Some(Address::from_str("0x9a7fe8e9852c684dd03ce9caea003f5fc0a3135b").unwrap())
+        );
+    }
+
+    #[test]
+    fn test_register_invalid_chains() {
+        let err = register(
+            "unknown-base",
+            serde_json::json!([{ "chain_id": 9_000_011, "base_chain_id": 9_999_999 }]),
+        )
+        .unwrap_err();
+        assert!(matches!(
+            err.origin(),
+            ChainConfigError::UnknownBaseChain(9_999_999)
+        ));
+
+        let err = register(
+            "unknown-hardfork",
+            serde_json::json!([{
+                "chain_id": 9_000_012,
+                "base_chain_id": TAIKO_A7.chain().id(),
+                "hardforks": { "nofork": { "block": 1 } }
+            }]),
+        )
+        .unwrap_err();
+        assert!(
+            matches!(err.origin(), ChainConfigError::UnknownHardfork(name) if name == "nofork")
+        );
+
+        let err = register(
+            "duplicate",
+            serde_json::json!([{ "chain_id": 9_000_013 }, { "chain_id": 9_000_013 }]),
+        )
+        .unwrap_err();
+        assert!(matches!(err, ChainConfigError::DuplicateChain(9_000_013)));
+
+        for chain_id in [9_000_011, 9_000_012, 9_000_013] {
+            assert!(get_chain_spec(chain_id).is_none());
+        }
+    }
+}
@@ -0,0 +1,320 @@
+use std::collections::BTreeMap;
+
+use reth_evm::BundleState;
+use reth_primitives::{Address, B256, U256};
+use serde::{Deserialize, Serialize};
+
+// the changes of the state made by the executed blocks, grouped by account
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StateDiff {
+    pub accounts: BTreeMap<Address, AccountDiff>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AccountDiff {
+    pub created: bool,
+    pub self_destructed: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub balance: Option<ValueDiff<U256>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub nonce: Option<ValueDiff<u64>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub code_hash: Option<ValueDiff<B256>>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub storage: BTreeMap<U256, ValueDiff<U256>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ValueDiff<T> {
+    pub from: T,
+    pub to: T,
+}
+
+impl<T: PartialEq> ValueDiff<T> {
+    fn new(from: T, to: T) -> Option<Self> {
+        if from == to {
+            return None;
+        }
+        Some(Self { from, to })
+    }
+
+    // `self` happens before `next`
+    fn merge(self, next: Self) -> Option<Self> {
+        Self::new(self.from, next.to)
+    }
+}
+
+impl AccountDiff {
+    pub fn is_empty(&self) -> bool {
+        !self.created
+            && !self.self_destructed
+            && self.balance.is_none()
+            && self.nonce.is_none()
+            && self.code_hash.is_none()
+            && self.storage.is_empty()
+    }
+
+    // `self` happens before `next`
+    fn merge(&mut self, next: AccountDiff) {
+        self.created |= next.created;
+        // recreated after destroyed is reported as created
+        self.self_destructed = next.self_destructed || (self.self_destructed && !next.created);
+        self.balance = merge_value(self.balance.take(), next.balance);
+        self.nonce = merge_value(self.nonce.take(), next.nonce);
+        self.code_hash = merge_value(self.code_hash.take(), next.code_hash);
+        for (slot, value) in next.storage {
+            match self.storage.remove(&slot) {
+                Some(prev) => {
+                    if let Some(value) = prev.merge(value) {
+                        self.storage.insert(slot, value);
+                    }
+                }
+                None => {
+                    self.storage.insert(slot, value);
+                }
+            }
+        }
+    }
+}
+
+fn merge_value<T: PartialEq>(
+    prev: Option<ValueDiff<T>>,
+    next: Option<ValueDiff<T>>,
+) -> Option<ValueDiff<T>> {
+    match (prev, next) {
+        (Some(prev), Some(next)) => prev.merge(next),
+        (prev, None) => prev,
+        (None, next) => next,
+    }
+}
+
+impl StateDiff {
+    pub fn from_bundle(state: &BundleState) -> Self {
+        let mut accounts = BTreeMap::new();
+        for (address, bundle_account) in &state.state {
+            let original = bundle_account.original_info.clone().unwrap_or_default();
+            let present = bundle_account.info.clone().unwrap_or_default();
+
+            let storage = bundle_account
+                .storage
+                .iter()
+                .filter_map(|(slot, value)| {
+                    let diff = ValueDiff::new(value.original_value(), value.present_value())?;
+                    Some((*slot, diff))
+                })
+                .collect();
+
+            // recreated after destroyed is reported as created, the same as `merge`
+            let destroyed = bundle_account.status.was_destroyed();
+            let exists = bundle_account.info.is_some();
+            let diff = AccountDiff {
+                created: exists && (bundle_account.original_info.is_none() || destroyed),
+                self_destructed: destroyed && !exists,
+                balance: ValueDiff::new(original.balance, present.balance),
+                nonce: ValueDiff::new(original.nonce, present.nonce),
+                code_hash: ValueDiff::new(original.code_hash, present.code_hash),
+                storage,
+            };
+            if !diff.is_empty() {
+                accounts.insert(*address, diff);
+            }
+        }
+        Self { accounts }
+    }
+
+    // merge the diff of the next block into `self`
+    pub fn merge(&mut self, next: StateDiff) {
+        for (address, diff) in next.accounts {
+            match self.accounts.get_mut(&address) {
+                Some(prev) => {
+                    prev.merge(diff);
+                    if prev.is_empty() {
+                        self.accounts.remove(&address);
+                    }
+                }
+                None => {
+                    self.accounts.insert(address, diff);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use reth_primitives::revm_primitives::{AccountInfo, EvmStorageSlot};
+    use reth_revm::db::{AccountStatus, BundleAccount};
+
+    use super::*;
+
+    fn info(nonce: u64, balance: u64) -> AccountInfo {
+        AccountInfo {
+            nonce,
+            balance: U256::from(balance),
+            ..Default::default()
+        }
+    }
+
+    // (slot, original, present)
+    fn bundle(
+        original: Option<AccountInfo>,
+        present: Option<AccountInfo>,
+        slots: &[(u64, u64, u64)],
+        status: AccountStatus,
+    ) -> BundleState {
+        let slots = slots
+            .iter()
+            .map(|(slot, from, to)| {
+                let slot_value = EvmStorageSlot::new_changed(U256::from(*from), U256::from(*to));
+                (U256::from(*slot), slot_value)
+            })
+            .collect();
+        let mut state = BundleState::default();
+        state.state.insert(
+            Address::repeat_byte(1),
+            BundleAccount::new(original, present, slots, status),
+        );
+        state
+    }
+
+    fn value<T>(from: T, to: T) -> Option<ValueDiff<T>> {
+        Some(ValueDiff { from, to })
+    }
+
+    fn storage(slots: &[(u64, u64, u64)]) -> BTreeMap<U256, ValueDiff<U256>> {
+        slots
+            .iter()
+            .map(|(slot, from, to)| {
+                let diff = ValueDiff {
+                    from: U256::from(*from),
+                    to: U256::from(*to),
+                };
+                (U256::from(*slot), diff)
+            })
+            .collect()
+    }
+
+    fn only_account(diff: StateDiff) -> AccountDiff {
+        assert_eq!(diff.accounts.len(), 1);
+        diff.accounts.into_values().next().unwrap()
+    }
+
+    #[test]
+    fn test_created_account() {
+        let state = bundle(
+            None,
+            Some(info(1, 100)),
+            &[(1, 0, 5)],
+            AccountStatus::InMemoryChange,
+        );
+        let diff = only_account(StateDiff::from_bundle(&state));
+        assert_eq!(
+            diff,
+            AccountDiff {
+                created: true,
+                self_destructed: false,
+                balance: value(U256::ZERO, U256::from(100)),
+                nonce: value(0, 1),
+                code_hash: None,
+                storage: storage(&[(1, 0, 5)]),
+            }
+        );
+    }
+
+    #[test]
+    fn test_self_destructed_account() {
+        let state = bundle(Some(info(1, 100)), None, &[], AccountStatus::Destroyed);
+        let diff = only_account(StateDiff::from_bundle(&state));
+        assert!(diff.self_destructed);
+        assert!(!diff.created);
+        assert_eq!(diff.balance, value(U256::from(100), U256::ZERO));
+        assert_eq!(diff.nonce, value(1, 0));
+
+        // destroyed and created again in the same block
+        let state = bundle(
+            Some(info(1, 100)),
+            Some(info(0, 5)),
+            &[],
+            AccountStatus::DestroyedChanged,
+        );
+        let diff = only_account(StateDiff::from_bundle(&state));
+        assert!(diff.created);
+        assert!(!diff.self_destructed);
+    }
+
+    #[test]
+    fn test_storage_cleared() {
+        let state = bundle(
+            Some(info(1, 100)),
+            Some(info(1, 100)),
+            &[(1, 5, 0), (2, 7, 0), (3, 9, 9)],
+            AccountStatus::Changed,
+        );
+        let diff = only_account(StateDiff::from_bundle(&state));
+        assert_eq!(
+            diff,
+            AccountDiff {
+                storage: storage(&[(1, 5, 0), (2, 7, 0)]),
+                ..Default::default()
+            }
+        );
+
+        // nothing changed at all
+        let state = bundle(
+            Some(info(1, 100)),
+            Some(info(1, 100)),
+            &[(3, 9, 9)],
+            AccountStatus::Changed,
+        );
+        assert!(StateDiff::from_bundle(&state).accounts.is_empty());
+    }
+
+    #[test]
+    fn test_merge() {
+        let addr = Address::repeat_byte(1);
+        let mut diff = StateDiff::default();
+        diff.accounts.insert(
+            addr,
+            AccountDiff {
+                balance: value(U256::ZERO, U256::from(10)),
+                storage: storage(&[(1, 1, 2), (2, 5, 6)]),
+                ..Default::default()
+            },
+        );
+        let mut next = StateDiff::default();
+        next.accounts.insert(
+            addr,
+            AccountDiff {
+                balance: value(U256::from(10), U256::from(20)),
+                nonce: value(0, 1),
+                storage: storage(&[(1, 2, 3), (2, 6, 5), (3, 0, 4)]),
+                ..Default::default()
+            },
+        );
+        diff.merge(next);
+        assert_eq!(
+            only_account(diff.clone()),
+            AccountDiff {
+                balance: value(U256::ZERO, U256::from(20)),
+                nonce: value(0, 1),
+                // the slot changed back to its original value is gone
+                storage: storage(&[(1, 1, 3), (3, 0, 4)]),
+                ..Default::default()
+            }
+        );
+
+        // the account changed back to where it was is gone
+        let mut revert = StateDiff::default();
+        revert.accounts.insert(
+            addr,
+            AccountDiff {
+                balance: value(U256::from(20), U256::ZERO),
+                nonce: value(1, 0),
+                storage: storage(&[(1, 3, 1), (3, 4, 0)]),
+                ..Default::default()
+            },
+        );
+        diff.merge(revert);
+        assert!(diff.accounts.is_empty());
+    }
+}
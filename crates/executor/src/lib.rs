@@ -7,5 +7,17 @@ pub use types::*;
 mod memdb;
 pub use memdb::*;
 
+mod trie;
+pub use trie::*;
+
+mod diff;
+pub use diff::*;
+
+mod report;
+pub use report::*;
+
+mod tracer;
+pub use tracer::*;
+
 mod block_executor;
 pub use block_executor::*;
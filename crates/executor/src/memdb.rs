@@ -1,21 +1,66 @@
 use std::{
-    collections::{BTreeMap, HashMap},
+    collections::{BTreeMap, BTreeSet, HashMap},
     sync::Arc,
 };
 
 use raiko_lib::primitives::mpt::{MptNode, StateAccount};
+use rayon::prelude::*;
 use reth_evm::execute::ProviderError;
 use reth_primitives::{
     keccak256,
-    revm_primitives::{db::Database, Account, AccountInfo, Bytecode},
+    revm_primitives::{db::Database, Account, AccountInfo, AccountStatus, Bytecode},
     Address, B256, U256,
 };
+use reth_revm::db::BundleAccount;
 
-use crate::{BlockDataProvider, ExecutionError};
+use crate::{
+    path_len, BlockDataProvider, DataProviderError, DbMetrics, ExecutionError, MissingWitness,
+};
+
+// everything of the pre state that has been read or written during the execution,
+// the witness only needs the trie paths to these keys.
+#[derive(Debug, Clone, Default)]
+pub struct AccessedState {
+    pub accounts: BTreeSet<Address>,
+    pub slots: BTreeMap<Address, BTreeSet<U256>>,
+    pub codes: BTreeSet<B256>,
+    pub block_hashes: BTreeSet<u64>,
+    // deleting a key may collapse a branch, which requires its siblings
+    pub deleted_accounts: BTreeSet<Address>,
+    pub deleted_slots: BTreeMap<Address, BTreeSet<U256>>,
+}
+
+impl AccessedState {
+    fn slot(&mut self, addr: Address, index: U256) {
+        self.slots.entry(addr).or_default().insert(index);
+    }
+
+    fn deleted_slot(&mut self, addr: Address, index: U256) {
+        self.slot(addr, index);
+        self.deleted_slots.entry(addr).or_default().insert(index);
+    }
+}
+
+// the decoded accounts and slots, it's only valid until the next apply_changes
+#[derive(Default)]
+struct StateCache {
+    accounts: HashMap<Address, Option<StateAccount>>,
+    slots: HashMap<(Address, U256), U256>,
+}
 
 pub struct MemDB<P: BlockDataProvider> {
     provider: Arc<P>,
     contracts: BTreeMap<B256, Bytecode>,
+    accessed: AccessedState,
+    cache: Option<StateCache>,
+    parallel: bool,
+    metrics: DbMetrics,
+
+    // the post state of the executed blocks, the provider is
+    // only consulted for the accounts which are not changed yet
+    state_trie: Option<MptNode>,
+    storage_tries: BTreeMap<Address, MptNode>,
+    block_hashes: BTreeMap<u64, B256>,
 }
 
 impl<P: BlockDataProvider> MemDB<P> {
@@ -23,6 +68,13 @@ impl<P: BlockDataProvider> MemDB<P> {
         let mut db = Self {
             provider,
             contracts: BTreeMap::new(),
+            accessed: AccessedState::default(),
+            cache: Some(StateCache::default()),
+            parallel: true,
+            metrics: DbMetrics::default(),
+            state_trie: None,
+            storage_tries: BTreeMap::new(),
+            block_hashes: BTreeMap::new(),
         };
         db.init();
         db
@@ -35,87 +87,240 @@ impl<P: BlockDataProvider> MemDB<P> {
         }
     }
 
+    // the cache is enabled by default
+    pub fn with_cache(mut self, enabled: bool) -> Self {
+        self.cache = enabled.then(StateCache::default);
+        self
+    }
+
+    // update the storage tries in parallel, enabled by default
+    pub fn with_parallel(mut self, enabled: bool) -> Self {
+        self.parallel = enabled;
+        self
+    }
+
+    pub fn accessed(&self) -> &AccessedState {
+        &self.accessed
+    }
+
+    // the counters collected since the last call
+    pub fn take_metrics(&mut self) -> DbMetrics {
+        std::mem::take(&mut self.metrics)
+    }
+
     fn get_acc(&mut self, addr: Address) -> Result<Option<StateAccount>, ProviderError> {
-        let Some(acc) = self.provider.get_acc::<StateAccount>(addr)? else {
-            return Ok(None);
+        self.accessed.accounts.insert(addr);
+        if let Some(acc) = self.cache.as_ref().and_then(|n| n.accounts.get(&addr)) {
+            return Ok(acc.clone());
+        }
+        let acc = self.load_acc(addr)?;
+        if let Some(cache) = &mut self.cache {
+            cache.accounts.insert(addr, acc.clone());
+        }
+        Ok(acc)
+    }
+
+    fn load_acc(&mut self, addr: Address) -> Result<Option<StateAccount>, ProviderError> {
+        let key = keccak256(addr);
+        let Some(state_trie) = &self.state_trie else {
+            if self.provider.has_witness() {
+                let state_trie = self
+                    .provider
+                    .state_trie()
+                    .map_err(|err| ProviderError::RPC(format!("get state trie: {:?}", err)))?;
+                self.metrics.trie_nodes_read += path_len(state_trie, key.as_slice());
+            }
+            return self.provider.get_acc::<StateAccount>(addr);
         };
-        Ok(Some(acc))
+        self.metrics.trie_nodes_read += path_len(state_trie, key.as_slice());
+        state_trie
+            .get_rlp::<StateAccount>(key.as_slice())
+            .map_err(|err| ProviderError::RPC(format!("get account[{:?}] fail: {}", addr, err)))
     }
 
+    fn load_slot(
+        &mut self,
+        address: Address,
+        root: B256,
+        index: U256,
+    ) -> Result<U256, ProviderError> {
+        let slot_key = keccak256(index.to_be_bytes::<32>());
+        // the storage trie has been changed by the previous blocks
+        if let Some(storage_trie) = self.storage_tries.get(&address) {
+            self.metrics.trie_nodes_read += path_len(storage_trie, slot_key.as_slice());
+            let result = storage_trie
+                .get_rlp::<U256>(slot_key.as_slice())
+                .map_err(|err| {
+                    ProviderError::RPC(format!(
+                        "fetch slot[addr={:?},index={}] fail: {}",
+                        address, index, err
+                    ))
+                })?;
+            return Ok(result.unwrap_or_default());
+        }
+
+        if self.provider.has_witness() {
+            if let Some(storage_trie) = self.provider.storage_state_trie(address) {
+                self.metrics.trie_nodes_read += path_len(storage_trie, slot_key.as_slice());
+            }
+        }
+        let result = self
+            .provider
+            .get_slot::<U256>(address, root, index)?
+            .unwrap_or_default();
+        Ok(result)
+    }
+
+    // make the block hash available to the blocks executed after it
+    pub fn insert_block_hash(&mut self, number: u64, hash: B256) {
+        self.block_hashes.insert(number, hash);
+    }
+
+    // apply the changes to the state and return the new state root,
+    // the following reads will see the post state.
+    //
+    // the storage tries are updated and hashed in parallel,
+    // then the state trie is updated once in the order of the addresses.
     pub fn apply_changes(
-        &self,
+        &mut self,
         changes: HashMap<Address, Account>,
-    ) -> Result<MptNode, ExecutionError> {
-        let mut account_touched = 0;
-        let mut storage_touched = 0;
+    ) -> Result<B256, ExecutionError> {
+        if let Some(cache) = &mut self.cache {
+            *cache = StateCache::default();
+        }
 
-        let mut state_trie = self.provider.state_trie().clone();
-        for (address, account) in changes {
-            if account.status.is_empty() {
-                continue;
-            }
+        let mut state_trie = match self.state_trie.take() {
+            Some(state_trie) => state_trie,
+            None => self.provider.state_trie()?.clone(),
+        };
+
+        let mut changes = changes
+            .into_iter()
+            .filter(|(_, account)| !account.status.is_empty())
+            .collect::<Vec<_>>();
+        changes.sort_by_key(|(address, _)| *address);
+        self.metrics.accounts_touched += changes.len();
 
-            // compute the index of the current account in the state trie
-            let state_trie_index = keccak256(address).0;
+        let mut updates = Vec::with_capacity(changes.len());
+        for (address, account) in changes {
+            self.accessed.accounts.insert(address);
 
             if account.is_selfdestructed() {
+                self.accessed.deleted_accounts.insert(address);
                 state_trie
-                    .delete(&state_trie_index)
+                    .delete(&keccak256(address).0)
                     .map_err(ExecutionError::DeleteAccount(&address))?;
+                self.storage_tries.insert(address, MptNode::default());
                 continue;
             }
 
-            account_touched += 1;
-
-            let state_storage = &account.storage;
-            let storage_root = {
-                // getting a mutable reference is more efficient than calling remove
-                // every account must have an entry, even newly created accounts
-                let mut storage_trie = self.provider.storage_state_trie(address).clone();
-
-                // for cleared accounts always start from the empty trie
-                if account.is_selfdestructed() {
-                    storage_trie.clear();
-                }
-
-                // apply all new storage entries for the current account (address)
-                for (key, value) in state_storage {
-                    let storage_trie_index = keccak256(key.to_be_bytes::<32>()).0;
-                    if value.present_value().is_zero() {
-                        storage_trie
-                            .delete(&storage_trie_index)
-                            .map_err(ExecutionError::DeleteStorage(key))?;
-                    } else {
-                        storage_trie
-                            .insert_rlp(&storage_trie_index, value.present_value())
-                            .map_err(ExecutionError::SetStorage(key))?;
-                    }
+            if let Some(code) = &account.info.code {
+                self.contracts
+                    .entry(account.info.code_hash)
+                    .or_insert_with(|| code.clone());
+            }
+            self.metrics.slots_touched += account.storage.len();
+            for (key, value) in &account.storage {
+                match value.present_value().is_zero() {
+                    true => self.accessed.deleted_slot(address, *key),
+                    false => self.accessed.slot(address, *key),
                 }
+            }
 
-                storage_touched += 1;
-
-                storage_trie.hash()
+            // every account must have an entry, even newly created accounts
+            let storage_trie = match self.storage_tries.remove(&address) {
+                Some(storage_trie) => storage_trie,
+                None => self
+                    .provider
+                    .storage_state_trie(address)
+                    .ok_or_else(|| {
+                        DataProviderError::IncompleteWitness(vec![MissingWitness::StorageTrie(
+                            address,
+                        )])
+                    })?
+                    .clone(),
             };
+            updates.push((address, account, storage_trie));
+        }
 
+        let updates = match self.parallel {
+            true => updates
+                .into_par_iter()
+                .map(update_storage_trie)
+                .collect::<Result<Vec<_>, _>>()?,
+            false => updates
+                .into_iter()
+                .map(update_storage_trie)
+                .collect::<Result<Vec<_>, _>>()?,
+        };
+
+        for (address, account, storage_trie) in updates {
             let state_account = StateAccount {
                 nonce: account.info.nonce,
                 balance: account.info.balance,
-                storage_root,
+                storage_root: storage_trie.hash(),
                 code_hash: account.info.code_hash,
             };
             state_trie
-                .insert_rlp(&state_trie_index, state_account)
+                .insert_rlp(&keccak256(address).0, state_account)
                 .map_err(ExecutionError::SetAccount(&address))?;
+            self.storage_tries.insert(address, storage_trie);
         }
 
-        log::debug!(
-            "apply changes: account_touched: {}, storage_touched: {}",
-            account_touched,
-            storage_touched,
-        );
+        let state_root = state_trie.hash();
+        self.state_trie = Some(state_trie);
+        Ok(state_root)
+    }
+}
 
-        Ok(state_trie)
+// the change of the account made by the block, in the form `apply_changes` takes.
+//
+// an account destroyed and then created again within the block is a created account,
+// its storage before the block is gone.
+pub fn account_change(bundle_account: BundleAccount) -> Account {
+    let exists = bundle_account.info.is_some();
+    let mut account = Account {
+        info: bundle_account.info.unwrap_or_default(),
+        storage: bundle_account.storage,
+        status: AccountStatus::default(),
+    };
+    account.mark_touch();
+    if bundle_account.status.was_destroyed() {
+        match exists {
+            true => account.mark_created(),
+            false => account.mark_selfdestruct(),
+        }
+    }
+    if bundle_account.original_info.is_none() {
+        account.mark_created();
     }
+    account
+}
+
+// apply the storage changes of the account, the root is hashed here
+// so the state trie update doesn't need to do it again.
+fn update_storage_trie(
+    (address, account, mut storage_trie): (Address, Account, MptNode),
+) -> Result<(Address, Account, MptNode), ExecutionError> {
+    // created accounts always start from the empty trie
+    if account.is_created() {
+        storage_trie.clear();
+    }
+
+    for (key, value) in &account.storage {
+        let storage_trie_index = keccak256(key.to_be_bytes::<32>()).0;
+        if value.present_value().is_zero() {
+            storage_trie
+                .delete(&storage_trie_index)
+                .map_err(ExecutionError::DeleteStorage(key))?;
+        } else {
+            storage_trie
+                .insert_rlp(&storage_trie_index, value.present_value())
+                .map_err(ExecutionError::SetStorage(key))?;
+        }
+    }
+    storage_trie.hash();
+    Ok((address, account, storage_trie))
 }
 
 impl<P: BlockDataProvider> Database for MemDB<P> {
@@ -132,23 +337,391 @@ impl<P: BlockDataProvider> Database for MemDB<P> {
     }
 
     fn block_hash(&mut self, number: U256) -> Result<B256, Self::Error> {
-        Ok(self.provider.block_hash(number.to()))
+        let number = number.to();
+        self.accessed.block_hashes.insert(number);
+        if let Some(hash) = self.block_hashes.get(&number) {
+            return Ok(*hash);
+        }
+        self.provider.block_hash(number)
     }
 
     fn code_by_hash(&mut self, code_hash: B256) -> Result<Bytecode, Self::Error> {
-        self.contracts
-            .get(&code_hash)
-            .cloned()
-            .ok_or(ProviderError::StateForHashNotFound(code_hash))
+        self.accessed.codes.insert(code_hash);
+        if let Some(code) = self.contracts.get(&code_hash) {
+            self.metrics.code_bytes_loaded += code.len();
+            return Ok(code.clone());
+        }
+        let code = self
+            .provider
+            .code_by_hash(code_hash)
+            .map(Bytecode::new_raw)
+            .ok_or(ProviderError::StateForHashNotFound(code_hash))?;
+        self.metrics.code_bytes_loaded += code.len();
+        self.contracts.insert(code_hash, code.clone());
+        Ok(code)
     }
 
     fn storage(&mut self, address: Address, index: U256) -> Result<U256, Self::Error> {
-        let acc = self.get_acc(address)?.expect("should have storage");
+        let Some(acc) = self.get_acc(address)? else {
+            return Ok(U256::ZERO);
+        };
+        self.accessed.slot(address, index);
+        if let Some(value) = self
+            .cache
+            .as_ref()
+            .and_then(|n| n.slots.get(&(address, index)))
+        {
+            return Ok(*value);
+        }
+        let value = self.load_slot(address, acc.storage_root, index)?;
+        if let Some(cache) = &mut self.cache {
+            cache.slots.insert((address, index), value);
+        }
+        Ok(value)
+    }
+}
 
-        let result = self
-            .provider
-            .get_slot::<U256>(address, acc.storage_root, index)?
-            .unwrap_or_default();
-        Ok(result)
+#[cfg(test)]
+mod test {
+    use proptest::prelude::*;
+    use reth_primitives::{
+        revm_primitives::{EvmStorageSlot, KECCAK_EMPTY},
+        Block, BlockWithSenders, Bytes,
+    };
+    use reth_revm::db::AccountStatus as BundleStatus;
+
+    use super::*;
+    use crate::DataProviderResult;
+
+    type Storage = BTreeMap<U256, U256>;
+
+    #[derive(Debug, Clone, PartialEq)]
+    struct TestAccount {
+        nonce: u64,
+        balance: U256,
+        storage: Storage,
+    }
+
+    #[derive(Debug, Clone)]
+    enum Change {
+        // the zero values delete the slots
+        Update {
+            nonce: u64,
+            balance: u64,
+            storage: Vec<(u8, u64)>,
+        },
+        SelfDestruct,
+        // self destructed and created again in the same block
+        Recreate {
+            nonce: u64,
+            balance: u64,
+            storage: Vec<(u8, u64)>,
+        },
+    }
+
+    struct TestProvider {
+        state_trie: MptNode,
+        storage_tries: BTreeMap<Address, MptNode>,
+        empty: MptNode,
+    }
+
+    impl BlockDataProvider for TestProvider {
+        type ExtData = ();
+
+        fn chain_id(&self) -> u64 {
+            1
+        }
+
+        fn blocks(&self) -> &[Block] {
+            &[]
+        }
+
+        fn contract_codes(&self) -> &[Bytes] {
+            &[]
+        }
+
+        fn block_hash(&self, number: u64) -> Result<B256, ProviderError> {
+            Err(ProviderError::HeaderNotFound(number.into()))
+        }
+
+        fn state_trie(&self) -> DataProviderResult<&MptNode> {
+            Ok(&self.state_trie)
+        }
+
+        // the accounts which don't exist have empty storage
+        fn storage_state_trie(&self, addr: Address) -> Option<&MptNode> {
+            Some(self.storage_tries.get(&addr).unwrap_or(&self.empty))
+        }
+
+        fn get_acc<T: alloy_rlp::Decodable>(
+            &self,
+            key: Address,
+        ) -> Result<Option<T>, ProviderError> {
+            self.state_trie
+                .get_rlp(keccak256(key).as_slice())
+                .map_err(|err| ProviderError::RPC(err.to_string()))
+        }
+
+        fn get_slot<T: alloy_rlp::Decodable>(
+            &self,
+            key: Address,
+            _root: B256,
+            slot: U256,
+        ) -> Result<Option<T>, ProviderError> {
+            let Some(storage_trie) = self.storage_tries.get(&key) else {
+                return Ok(None);
+            };
+            storage_trie
+                .get_rlp(keccak256(slot.to_be_bytes::<32>()).as_slice())
+                .map_err(|err| ProviderError::RPC(err.to_string()))
+        }
+
+        fn ext_data(&self, _idx: usize) -> Self::ExtData {}
+
+        fn check_witness(&self, _blocks: &[BlockWithSenders]) -> DataProviderResult<()> {
+            Ok(())
+        }
+    }
+
+    fn build_tries(
+        state: &BTreeMap<Address, TestAccount>,
+    ) -> (MptNode, BTreeMap<Address, MptNode>) {
+        let mut state_trie = MptNode::default();
+        let mut storage_tries = BTreeMap::new();
+        for (addr, acc) in state {
+            let mut storage_trie = MptNode::default();
+            for (slot, value) in &acc.storage {
+                storage_trie
+                    .insert_rlp(&keccak256(slot.to_be_bytes::<32>()).0, *value)
+                    .unwrap();
+            }
+            let state_account = StateAccount {
+                nonce: acc.nonce,
+                balance: acc.balance,
+                storage_root: storage_trie.hash(),
+                code_hash: KECCAK_EMPTY,
+            };
+            state_trie
+                .insert_rlp(&keccak256(addr).0, state_account)
+                .unwrap();
+            storage_tries.insert(*addr, storage_trie);
+        }
+        (state_trie, storage_tries)
+    }
+
+    fn account_info(acc: &TestAccount) -> AccountInfo {
+        AccountInfo {
+            balance: acc.balance,
+            nonce: acc.nonce,
+            code_hash: KECCAK_EMPTY,
+            code: None,
+        }
+    }
+
+    // turn the change into the account the executor collects from the bundle,
+    // and apply it to the expected state
+    fn apply_change(
+        state: &mut BTreeMap<Address, TestAccount>,
+        addr: Address,
+        change: &Change,
+    ) -> Account {
+        let original = state.get(&addr).cloned();
+        let original_info = original.as_ref().map(account_info);
+        let original_slot = |slot: U256| {
+            original
+                .as_ref()
+                .and_then(|n| n.storage.get(&slot).cloned())
+                .unwrap_or_default()
+        };
+
+        let bundle_account = match change {
+            Change::Update {
+                nonce,
+                balance,
+                storage,
+            } => {
+                let acc = state.entry(addr).or_insert(TestAccount {
+                    nonce: 0,
+                    balance: U256::ZERO,
+                    storage: Storage::new(),
+                });
+                acc.nonce = *nonce;
+                acc.balance = U256::from(*balance);
+                let mut slots = Vec::new();
+                for (slot, value) in storage {
+                    let (slot, value) = (U256::from(*slot), U256::from(*value));
+                    match value.is_zero() {
+                        true => acc.storage.remove(&slot),
+                        false => acc.storage.insert(slot, value),
+                    };
+                    slots.push((
+                        slot,
+                        EvmStorageSlot::new_changed(original_slot(slot), value),
+                    ));
+                }
+                let status = match original_info {
+                    Some(_) => BundleStatus::Changed,
+                    None => BundleStatus::InMemoryChange,
+                };
+                let slots = slots.into_iter().collect();
+                BundleAccount::new(original_info, Some(account_info(acc)), slots, status)
+            }
+            Change::SelfDestruct => {
+                state.remove(&addr);
+                BundleAccount::new(
+                    original_info,
+                    None,
+                    Default::default(),
+                    BundleStatus::Destroyed,
+                )
+            }
+            Change::Recreate {
+                nonce,
+                balance,
+                storage,
+            } => {
+                let mut acc = TestAccount {
+                    nonce: *nonce,
+                    balance: U256::from(*balance),
+                    storage: Storage::new(),
+                };
+                let mut slots = Vec::new();
+                for (slot, value) in storage {
+                    let (slot, value) = (U256::from(*slot), U256::from(*value));
+                    if !value.is_zero() {
+                        acc.storage.insert(slot, value);
+                    }
+                    slots.push((
+                        slot,
+                        EvmStorageSlot::new_changed(original_slot(slot), value),
+                    ));
+                }
+                let info = account_info(&acc);
+                state.insert(addr, acc);
+                let slots = slots.into_iter().collect();
+                BundleAccount::new(
+                    original_info,
+                    Some(info),
+                    slots,
+                    BundleStatus::DestroyedChanged,
+                )
+            }
+        };
+        account_change(bundle_account)
+    }
+
+    fn storage_strategy() -> impl Strategy<Value = Vec<(u8, u64)>> {
+        prop::collection::vec((0u8..16, prop_oneof![Just(0u64), 1u64..1000]), 0..8)
+    }
+
+    fn account_strategy() -> impl Strategy<Value = TestAccount> {
+        (
+            0u64..100,
+            0u64..1_000_000,
+            prop::collection::btree_map(0u8..16, 1u64..1000, 0..8),
+        )
+            .prop_map(|(nonce, balance, storage)| TestAccount {
+                nonce,
+                balance: U256::from(balance),
+                storage: storage
+                    .into_iter()
+                    .map(|(slot, value)| (U256::from(slot), U256::from(value)))
+                    .collect(),
+            })
+    }
+
+    fn change_strategy() -> impl Strategy<Value = Change> {
+        prop_oneof![
+            3 => (0u64..100, 0u64..1_000_000, storage_strategy()).prop_map(
+                |(nonce, balance, storage)| Change::Update {
+                    nonce,
+                    balance,
+                    storage,
+                }
+            ),
+            1 => Just(Change::SelfDestruct),
+            1 => (0u64..100, 0u64..1_000_000, storage_strategy()).prop_map(
+                |(nonce, balance, storage)| Change::Recreate {
+                    nonce,
+                    balance,
+                    storage,
+                }
+            ),
+        ]
+    }
+
+    // the accounts after the pre state ones are the new accounts
+    fn run(
+        pre: BTreeMap<u8, TestAccount>,
+        blocks: Vec<BTreeMap<u8, Change>>,
+        parallel: bool,
+    ) -> Result<(), TestCaseError> {
+        let mut state = pre
+            .into_iter()
+            .map(|(idx, acc)| (Address::with_last_byte(idx), acc))
+            .collect::<BTreeMap<_, _>>();
+        let (state_trie, storage_tries) = build_tries(&state);
+        let provider = TestProvider {
+            state_trie,
+            storage_tries,
+            empty: MptNode::default(),
+        };
+        let mut db = MemDB::new(Arc::new(provider)).with_parallel(parallel);
+
+        for changes in blocks {
+            let changes = changes
+                .into_iter()
+                .filter_map(|(idx, change)| {
+                    let addr = Address::with_last_byte(idx);
+                    // self destructing a missing account is a no-op
+                    if matches!(change, Change::SelfDestruct) && !state.contains_key(&addr) {
+                        return None;
+                    }
+                    Some((addr, apply_change(&mut state, addr, &change)))
+                })
+                .collect::<HashMap<_, _>>();
+            let state_root = db.apply_changes(changes).unwrap();
+            let (want, _) = build_tries(&state);
+            prop_assert_eq!(state_root, want.hash());
+        }
+        Ok(())
+    }
+
+    proptest! {
+        #[test]
+        fn test_apply_changes(
+            pre in prop::collection::btree_map(0u8..16, account_strategy(), 0..8),
+            blocks in prop::collection::vec(
+                prop::collection::btree_map(0u8..24, change_strategy(), 0..8),
+                1..4,
+            ),
+            parallel in any::<bool>(),
+        ) {
+            run(pre, blocks, parallel)?;
+        }
+    }
+
+    #[test]
+    fn test_recreate_in_same_block() {
+        let mut storage = Storage::new();
+        storage.insert(U256::from(1), U256::from(10));
+        storage.insert(U256::from(2), U256::from(20));
+        let pre = BTreeMap::from([(
+            1,
+            TestAccount {
+                nonce: 1,
+                balance: U256::from(100),
+                storage,
+            },
+        )]);
+        let blocks = vec![BTreeMap::from([(
+            1,
+            Change::Recreate {
+                nonce: 0,
+                balance: 5,
+                storage: vec![(2, 7)],
+            },
+        )])];
+        run(pre, blocks, false).unwrap();
     }
 }
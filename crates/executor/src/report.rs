@@ -0,0 +1,42 @@
+use serde::{Deserialize, Serialize};
+
+// the metrics of the executed blocks, for sizing the hardware of the prover
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ExecutionReport {
+    pub blocks: Vec<BlockReport>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BlockReport {
+    pub number: u64,
+    pub gas_used: u64,
+    pub tx_count: usize,
+    pub accounts_touched: usize,
+    pub slots_touched: usize,
+    // the nodes walked through by the trie lookups which missed the cache
+    pub trie_nodes_read: usize,
+    pub code_bytes_loaded: usize,
+    // the timings in microseconds
+    pub sender_recovery_us: u64,
+    pub execution_us: u64,
+    pub trie_update_us: u64,
+}
+
+impl ExecutionReport {
+    pub fn gas_used(&self) -> u64 {
+        self.blocks.iter().map(|n| n.gas_used).sum()
+    }
+
+    pub fn tx_count(&self) -> usize {
+        self.blocks.iter().map(|n| n.tx_count).sum()
+    }
+}
+
+// the counters collected by MemDB while executing a block
+#[derive(Debug, Clone, Default)]
+pub struct DbMetrics {
+    pub accounts_touched: usize,
+    pub slots_touched: usize,
+    pub trie_nodes_read: usize,
+    pub code_bytes_loaded: usize,
+}
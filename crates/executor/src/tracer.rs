@@ -0,0 +1,371 @@
+use std::sync::{Arc, Mutex};
+
+use reth_chainspec::ChainSpec;
+use reth_evm::{ConfigureEvm, ConfigureEvmEnv};
+use reth_evm_ethereum::EthEvmConfig;
+use reth_primitives::{
+    revm_primitives::{CfgEnvWithHandlerCfg, EnvWithHandlerCfg, TxEnv},
+    Address, BlockWithSenders, Bytes, Header, TransactionSigned, B256, U256,
+};
+use reth_revm::{
+    interpreter::{
+        CallInputs, CallOutcome, CallScheme, CreateInputs, CreateOutcome, CreateScheme,
+        InstructionResult, Interpreter, OpCode,
+    },
+    Database, Evm, EvmContext, Inspector,
+};
+use serde::{Deserialize, Serialize};
+
+// the tracers comparable to the ones of geth's debug_traceBlock
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum TracerKind {
+    CallTracer,
+    StructLogger,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BlockTrace {
+    pub number: u64,
+    pub hash: B256,
+    pub txs: Vec<TxTrace>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TxTrace {
+    // none if the tx can't be matched with the block body
+    pub tx_hash: Option<B256>,
+    pub result: TraceResult,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum TraceResult {
+    Call(CallFrame),
+    StructLog(StructLogTrace),
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CallFrame {
+    #[serde(rename = "type")]
+    pub typ: String,
+    pub from: Address,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub to: Option<Address>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub value: Option<U256>,
+    pub gas: u64,
+    pub gas_used: u64,
+    pub input: Bytes,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub output: Option<Bytes>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub calls: Vec<CallFrame>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StructLogTrace {
+    pub gas: u64,
+    pub failed: bool,
+    pub return_value: Bytes,
+    pub struct_logs: Vec<StructLog>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StructLog {
+    pub pc: u64,
+    pub op: String,
+    pub gas: u64,
+    pub gas_cost: u64,
+    pub depth: u64,
+    pub stack: Vec<U256>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+// a cheap handle to the trace collector, it's passed into the evm as the external
+// context, so the traces are still available when the block execution fails.
+#[derive(Clone)]
+pub struct Tracer {
+    inner: Arc<Mutex<TracerInner>>,
+}
+
+struct TracerInner {
+    kind: TracerKind,
+    blocks: Vec<BlockTrace>,
+    // (sender, nonce, hash) of the txs of the current block
+    txs: Vec<(Address, u64, B256)>,
+
+    // the frames of the tx being executed
+    frames: Vec<CallFrame>,
+    tx_hash: Option<B256>,
+    struct_logs: Vec<StructLog>,
+    step_gas: u64,
+}
+
+impl Tracer {
+    pub fn new(kind: TracerKind) -> Self {
+        Self {
+            inner: Arc::new(Mutex::new(TracerInner {
+                kind,
+                blocks: Vec::new(),
+                txs: Vec::new(),
+                frames: Vec::new(),
+                tx_hash: None,
+                struct_logs: Vec::new(),
+                step_gas: 0,
+            })),
+        }
+    }
+
+    pub fn kind(&self) -> TracerKind {
+        self.inner.lock().unwrap().kind
+    }
+
+    // the traces collected after this call belong to `block`
+    pub fn start_block(&self, block: &BlockWithSenders) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.txs = block
+            .transactions_with_sender()
+            .map(|(sender, tx)| (*sender, tx.nonce(), tx.hash()))
+            .collect();
+        inner.blocks.push(BlockTrace {
+            number: block.number,
+            hash: block.header.hash_slow(),
+            txs: Vec::new(),
+        });
+    }
+
+    pub fn traces(&self) -> Vec<BlockTrace> {
+        self.inner.lock().unwrap().blocks.clone()
+    }
+}
+
+impl TracerInner {
+    fn enter<DB: Database>(&mut self, context: &EvmContext<DB>, frame: CallFrame) {
+        if self.frames.is_empty() {
+            let tx = &context.env.tx;
+            self.tx_hash = self
+                .txs
+                .iter()
+                .find(|(sender, nonce, _)| *sender == tx.caller && Some(*nonce) == tx.nonce)
+                .map(|(_, _, hash)| *hash);
+            self.struct_logs.clear();
+        }
+        self.frames.push(frame);
+    }
+
+    fn exit(&mut self, result: InstructionResult, output: &Bytes, gas_used: u64) {
+        let Some(mut frame) = self.frames.pop() else {
+            return;
+        };
+        frame.gas_used = gas_used;
+        if !output.is_empty() {
+            frame.output = Some(output.clone());
+        }
+        if !result.is_ok() {
+            frame.error = Some(match result.is_revert() {
+                true => "execution reverted".to_owned(),
+                false => format!("{:?}", result),
+            });
+        }
+
+        if let Some(parent) = self.frames.last_mut() {
+            parent.calls.push(frame);
+            return;
+        }
+
+        // the top frame is done, so is the tx
+        let result = match self.kind {
+            TracerKind::CallTracer => TraceResult::Call(frame),
+            TracerKind::StructLogger => TraceResult::StructLog(StructLogTrace {
+                gas: gas_used,
+                failed: !result.is_ok(),
+                return_value: output.clone(),
+                struct_logs: std::mem::take(&mut self.struct_logs),
+            }),
+        };
+        let tx_hash = self.tx_hash.take();
+        if let Some(block) = self.blocks.last_mut() {
+            block.txs.push(TxTrace { tx_hash, result });
+        }
+    }
+}
+
+impl<DB: Database> Inspector<DB> for Tracer {
+    fn step(&mut self, interp: &mut Interpreter, _: &mut EvmContext<DB>) {
+        let mut inner = self.inner.lock().unwrap();
+        if inner.kind != TracerKind::StructLogger {
+            return;
+        }
+        let op = interp.current_opcode();
+        let log = StructLog {
+            pc: interp.program_counter() as u64,
+            op: match OpCode::new(op) {
+                Some(op) => op.as_str().to_owned(),
+                None => format!("opcode {:#04x} not defined", op),
+            },
+            gas: interp.gas.remaining(),
+            gas_cost: 0,
+            depth: inner.frames.len() as u64,
+            stack: interp.stack.data().clone(),
+            error: None,
+        };
+        inner.step_gas = interp.gas.remaining();
+        inner.struct_logs.push(log);
+    }
+
+    fn step_end(&mut self, interp: &mut Interpreter, _: &mut EvmContext<DB>) {
+        let mut inner = self.inner.lock().unwrap();
+        if inner.kind != TracerKind::StructLogger {
+            return;
+        }
+        let gas_cost = inner.step_gas.saturating_sub(interp.gas.remaining());
+        if let Some(log) = inner.struct_logs.last_mut() {
+            log.gas_cost = gas_cost;
+            if interp.instruction_result.is_error() {
+                log.error = Some(format!("{:?}", interp.instruction_result));
+            }
+        }
+    }
+
+    fn call(
+        &mut self,
+        context: &mut EvmContext<DB>,
+        inputs: &mut CallInputs,
+    ) -> Option<CallOutcome> {
+        let typ = match inputs.scheme {
+            CallScheme::Call => "CALL",
+            CallScheme::CallCode => "CALLCODE",
+            CallScheme::DelegateCall => "DELEGATECALL",
+            CallScheme::StaticCall => "STATICCALL",
+        };
+        let value = match inputs.scheme {
+            CallScheme::DelegateCall | CallScheme::StaticCall => None,
+            _ => Some(inputs.value.get()),
+        };
+        let frame = CallFrame {
+            typ: typ.to_owned(),
+            from: inputs.caller,
+            to: Some(inputs.target_address),
+            value,
+            gas: inputs.gas_limit,
+            input: inputs.input.clone(),
+            ..Default::default()
+        };
+        self.inner.lock().unwrap().enter(context, frame);
+        None
+    }
+
+    fn call_end(
+        &mut self,
+        _: &mut EvmContext<DB>,
+        _: &CallInputs,
+        outcome: CallOutcome,
+    ) -> CallOutcome {
+        self.inner.lock().unwrap().exit(
+            outcome.result.result,
+            &outcome.result.output,
+            outcome.result.gas.spent(),
+        );
+        outcome
+    }
+
+    fn create(
+        &mut self,
+        context: &mut EvmContext<DB>,
+        inputs: &mut CreateInputs,
+    ) -> Option<CreateOutcome> {
+        let typ = match inputs.scheme {
+            CreateScheme::Create => "CREATE",
+            CreateScheme::Create2 { .. } => "CREATE2",
+        };
+        let frame = CallFrame {
+            typ: typ.to_owned(),
+            from: inputs.caller,
+            value: Some(inputs.value),
+            gas: inputs.gas_limit,
+            input: inputs.init_code.clone(),
+            ..Default::default()
+        };
+        self.inner.lock().unwrap().enter(context, frame);
+        None
+    }
+
+    fn create_end(
+        &mut self,
+        _: &mut EvmContext<DB>,
+        _: &CreateInputs,
+        outcome: CreateOutcome,
+    ) -> CreateOutcome {
+        let mut inner = self.inner.lock().unwrap();
+        if let Some(frame) = inner.frames.last_mut() {
+            frame.to = outcome.address;
+        }
+        inner.exit(
+            outcome.result.result,
+            &outcome.result.output,
+            outcome.result.gas.spent(),
+        );
+        outcome
+    }
+}
+
+// the evm config of the executor with the tracer attached to every evm it builds,
+// the evm is built by the inner config so the traced execution is the proved one.
+#[derive(Clone)]
+pub struct TracingEvmConfig {
+    inner: EthEvmConfig,
+    tracer: Tracer,
+}
+
+impl TracingEvmConfig {
+    pub fn new(tracer: Tracer) -> Self {
+        Self {
+            inner: EthEvmConfig::default(),
+            tracer,
+        }
+    }
+}
+
+impl ConfigureEvmEnv for TracingEvmConfig {
+    fn fill_tx_env(tx_env: &mut TxEnv, transaction: &TransactionSigned, sender: Address) {
+        EthEvmConfig::fill_tx_env(tx_env, transaction, sender)
+    }
+
+    fn fill_cfg_env(
+        cfg_env: &mut CfgEnvWithHandlerCfg,
+        chain_spec: &ChainSpec,
+        header: &Header,
+        total_difficulty: U256,
+    ) {
+        EthEvmConfig::fill_cfg_env(cfg_env, chain_spec, header, total_difficulty)
+    }
+}
+
+impl ConfigureEvm for TracingEvmConfig {
+    type DefaultExternalContext<'a> = Tracer;
+
+    fn evm<'a, DB: Database + 'a>(
+        &'a self,
+        db: DB,
+    ) -> Evm<'a, Self::DefaultExternalContext<'a>, DB> {
+        self.inner.evm_with_inspector(db, self.tracer.clone())
+    }
+
+    fn evm_with_env<'a, DB: Database + 'a>(
+        &'a self,
+        db: DB,
+        env: EnvWithHandlerCfg,
+    ) -> Evm<'a, Self::DefaultExternalContext<'a>, DB> {
+        self.inner
+            .evm_with_env_and_inspector(db, env, self.tracer.clone())
+    }
+}
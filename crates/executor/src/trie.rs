@@ -0,0 +1,318 @@
+use std::collections::HashMap;
+
+use raiko_lib::primitives::mpt::{MptNode, MptNodeData, MptNodeReference};
+
+// collect all the resolved nodes of the trie, indexed by their reference
+pub fn collect_nodes(node: &MptNode, nodes: &mut HashMap<MptNodeReference, MptNode>) {
+    match node.as_data() {
+        MptNodeData::Null | MptNodeData::Digest(_) => return,
+        MptNodeData::Branch(children) => {
+            for child in children.iter().flatten() {
+                collect_nodes(child, nodes);
+            }
+        }
+        MptNodeData::Extension(_, child) => collect_nodes(child, nodes),
+        MptNodeData::Leaf(_, _) => {}
+    }
+    nodes.insert(node.reference(), node.clone());
+}
+
+// replace the digests of the trie with the resolved nodes that have the same reference,
+// the hash of the trie never changes.
+pub fn resolve_nodes(node: &MptNode, nodes: &HashMap<MptNodeReference, MptNode>) -> MptNode {
+    match node.as_data() {
+        MptNodeData::Null | MptNodeData::Leaf(_, _) => node.clone(),
+        MptNodeData::Branch(children) => {
+            let children = std::array::from_fn(|idx| {
+                children[idx]
+                    .as_ref()
+                    .map(|child| Box::new(resolve_nodes(child, nodes)))
+            });
+            MptNodeData::Branch(children).into()
+        }
+        MptNodeData::Extension(prefix, child) => {
+            MptNodeData::Extension(prefix.clone(), Box::new(resolve_nodes(child, nodes))).into()
+        }
+        MptNodeData::Digest(digest) => match nodes.get(&MptNodeReference::Digest(*digest)) {
+            Some(resolved) => resolve_nodes(resolved, nodes),
+            None => node.clone(),
+        },
+    }
+}
+
+// the key of the trie to keep, and whether it's going to be deleted
+pub struct PruneKey {
+    pub nibs: Vec<u8>,
+    pub deleted: bool,
+}
+
+impl PruneKey {
+    pub fn new(key: &[u8], deleted: bool) -> Self {
+        let nibs = key.iter().flat_map(|n| [n >> 4, n & 0xf]).collect();
+        Self { nibs, deleted }
+    }
+}
+
+// keep the nodes on the paths to the keys and replace everything else with digests,
+// the hash of the trie never changes.
+//
+// the siblings of the branches on the path of a deleted key are kept one level deep,
+// the branch may be collapsed into its only remaining child.
+pub fn prune_trie(node: &MptNode, keys: &[PruneKey]) -> MptNode {
+    let keys = keys
+        .iter()
+        .map(|key| (key.nibs.as_slice(), key.deleted))
+        .collect::<Vec<_>>();
+    prune_node(node, &keys)
+}
+
+fn prune_node(node: &MptNode, keys: &[(&[u8], bool)]) -> MptNode {
+    if keys.is_empty() {
+        return to_digest(node);
+    }
+    match node.as_data() {
+        MptNodeData::Null | MptNodeData::Leaf(_, _) | MptNodeData::Digest(_) => node.clone(),
+        MptNodeData::Branch(children) => {
+            let deleted = keys.iter().any(|(_, deleted)| *deleted);
+            let children = std::array::from_fn(|idx| {
+                let child = children[idx].as_ref()?;
+                let child_keys = keys
+                    .iter()
+                    .filter(|(nibs, _)| nibs.first() == Some(&(idx as u8)))
+                    .map(|(nibs, deleted)| (&nibs[1..], *deleted))
+                    .collect::<Vec<_>>();
+                let child = match (child_keys.is_empty(), deleted) {
+                    (true, true) => to_shallow(child),
+                    _ => prune_node(child, &child_keys),
+                };
+                Some(Box::new(child))
+            });
+            MptNodeData::Branch(children).into()
+        }
+        MptNodeData::Extension(prefix, child) => {
+            let prefix_nibs = decode_prefix(prefix);
+            let child_keys = keys
+                .iter()
+                .filter(|(nibs, _)| nibs.starts_with(&prefix_nibs))
+                .map(|(nibs, deleted)| (&nibs[prefix_nibs.len()..], *deleted))
+                .collect::<Vec<_>>();
+            let child = prune_node(child, &child_keys);
+            MptNodeData::Extension(prefix.clone(), Box::new(child)).into()
+        }
+    }
+}
+
+// the number of resolved nodes walked through to look up the key
+pub fn path_len(node: &MptNode, key: &[u8]) -> usize {
+    let nibs = PruneKey::new(key, false).nibs;
+    let mut nibs = nibs.as_slice();
+    let mut node = node;
+    let mut count = 0;
+    loop {
+        match node.as_data() {
+            MptNodeData::Null | MptNodeData::Digest(_) => return count,
+            MptNodeData::Leaf(_, _) => return count + 1,
+            MptNodeData::Branch(children) => {
+                count += 1;
+                let Some((idx, rest)) = nibs.split_first() else {
+                    return count;
+                };
+                let Some(child) = &children[*idx as usize] else {
+                    return count;
+                };
+                node = child;
+                nibs = rest;
+            }
+            MptNodeData::Extension(prefix, child) => {
+                count += 1;
+                let prefix = decode_prefix(prefix);
+                let Some(rest) = nibs.strip_prefix(prefix.as_slice()) else {
+                    return count;
+                };
+                node = child;
+                nibs = rest;
+            }
+        }
+    }
+}
+
+// the node itself with all its children replaced by digests
+fn to_shallow(node: &MptNode) -> MptNode {
+    match node.as_data() {
+        MptNodeData::Branch(children) => {
+            let children = std::array::from_fn(|idx| {
+                children[idx]
+                    .as_ref()
+                    .map(|child| Box::new(to_digest(child)))
+            });
+            MptNodeData::Branch(children).into()
+        }
+        MptNodeData::Extension(prefix, child) => {
+            MptNodeData::Extension(prefix.clone(), Box::new(to_digest(child))).into()
+        }
+        _ => node.clone(),
+    }
+}
+
+// the nodes shorter than 32 bytes are inlined into their parent, they can't be digests
+fn to_digest(node: &MptNode) -> MptNode {
+    match node.reference() {
+        MptNodeReference::Digest(digest) => MptNodeData::Digest(digest).into(),
+        MptNodeReference::Bytes(_) => node.clone(),
+    }
+}
+
+// decode the hex-prefix encoded path of the extension
+pub fn decode_prefix(prefix: &[u8]) -> Vec<u8> {
+    let Some(first) = prefix.first() else {
+        return Vec::new();
+    };
+    let mut nibs = Vec::with_capacity(prefix.len() * 2);
+    // odd length, the first nibble is part of the path
+    if (first >> 4) & 1 == 1 {
+        nibs.push(first & 0xf);
+    }
+    for n in &prefix[1..] {
+        nibs.push(n >> 4);
+        nibs.push(n & 0xf);
+    }
+    nibs
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // the key by its leading nibbles, padded with zeros to 32 bytes
+    fn key(nibs: &[u8]) -> Vec<u8> {
+        let mut nibs = nibs.to_vec();
+        nibs.resize(64, 0);
+        nibs.chunks(2).map(|n| (n[0] << 4) | n[1]).collect()
+    }
+
+    // the values are long enough for the leaves not to be inlined
+    fn build_trie(keys: &[Vec<u8>]) -> MptNode {
+        let mut trie = MptNode::default();
+        for (idx, key) in keys.iter().enumerate() {
+            trie.insert(key, vec![idx as u8 + 1; 32]).unwrap();
+        }
+        trie
+    }
+
+    // delete the key from both tries, the pruned one must end up with the same root
+    fn assert_delete(trie: &MptNode, pruned: &MptNode, key: &[u8]) {
+        let (mut trie, mut pruned) = (trie.clone(), pruned.clone());
+        assert!(trie.delete(key).unwrap());
+        assert!(pruned.delete(key).unwrap());
+        assert_eq!(pruned.hash(), trie.hash());
+    }
+
+    #[test]
+    fn test_decode_prefix() {
+        assert_eq!(decode_prefix(&[]), Vec::<u8>::new());
+        assert_eq!(decode_prefix(&[0x00]), Vec::<u8>::new());
+        assert_eq!(decode_prefix(&[0x00, 0xab, 0xcd]), vec![0xa, 0xb, 0xc, 0xd]);
+        assert_eq!(decode_prefix(&[0x1a]), vec![0xa]);
+        assert_eq!(decode_prefix(&[0x1a, 0xbc]), vec![0xa, 0xb, 0xc]);
+    }
+
+    #[test]
+    fn test_prune_extension() {
+        // the root is an extension of odd and even length
+        for len in [3, 4] {
+            let prefix = [0xa, 0xb, 0xc, 0xd][..len].to_vec();
+            let keys = [1u8, 2].map(|n| key(&[prefix.clone(), vec![n]].concat()));
+            let trie = build_trie(&keys);
+            let MptNodeData::Extension(encoded, _) = trie.as_data() else {
+                panic!("the root is not an extension: {:?}", trie);
+            };
+            assert_eq!(decode_prefix(encoded), prefix);
+
+            let pruned = prune_trie(&trie, &[PruneKey::new(&keys[0], false)]);
+            assert_eq!(pruned.hash(), trie.hash());
+            assert_eq!(pruned.get(&keys[0]).unwrap(), trie.get(&keys[0]).unwrap());
+            assert!(pruned.get(&keys[1]).is_err());
+
+            let pruned = prune_trie(&trie, &[]);
+            assert!(matches!(pruned.as_data(), MptNodeData::Digest(_)));
+            assert_eq!(pruned.hash(), trie.hash());
+        }
+    }
+
+    #[test]
+    fn test_to_shallow() {
+        let keys = (0..4).map(|n| key(&[n])).collect::<Vec<_>>();
+        let trie = build_trie(&keys);
+        let shallow = to_shallow(&trie);
+        assert_eq!(shallow.hash(), trie.hash());
+        let MptNodeData::Branch(children) = shallow.as_data() else {
+            panic!("the root is not a branch: {:?}", trie);
+        };
+        assert_eq!(children.iter().flatten().count(), keys.len());
+        for child in children.iter().flatten() {
+            assert!(matches!(child.as_data(), MptNodeData::Digest(_)));
+        }
+
+        // a leaf has nothing to be replaced
+        let MptNodeData::Branch(children) = trie.as_data() else {
+            unreachable!()
+        };
+        let leaf = children[0].as_ref().unwrap();
+        assert_eq!(to_shallow(leaf).as_data(), leaf.as_data());
+    }
+
+    #[test]
+    fn test_prune_delete_to_single_child() {
+        // the root branch is left with the leaf of keys[1]
+        let keys = vec![key(&[1]), key(&[2])];
+        let trie = build_trie(&keys);
+        let pruned = prune_trie(&trie, &[PruneKey::new(&keys[0], true)]);
+        assert_eq!(pruned.hash(), trie.hash());
+        assert_delete(&trie, &pruned, &keys[0]);
+
+        // without the sibling, its digest can't be merged into the new root
+        let mut pruned = prune_trie(&trie, &[PruneKey::new(&keys[0], false)]);
+        let mut deleted = trie.clone();
+        deleted.delete(&keys[0]).unwrap();
+        pruned.delete(&keys[0]).unwrap();
+        assert_ne!(pruned.hash(), deleted.hash());
+    }
+
+    #[test]
+    fn test_prune_delete_extension_merge() {
+        // the root branch has a leaf at 1, and an extension of [3, 4] at 2
+        // over the branch of the other two keys
+        let keys = vec![key(&[1]), key(&[2, 3, 4, 1]), key(&[2, 3, 4, 2])];
+        let trie = build_trie(&keys);
+        let MptNodeData::Branch(children) = trie.as_data() else {
+            panic!("the root is not a branch: {:?}", trie);
+        };
+        let MptNodeData::Extension(encoded, _) = children[2].as_ref().unwrap().as_data() else {
+            panic!("not an extension: {:?}", children[2]);
+        };
+        assert_eq!(decode_prefix(encoded), vec![3, 4]);
+
+        // the root is collapsed into the extension, which is extended to [2, 3, 4]
+        let pruned = prune_trie(&trie, &[PruneKey::new(&keys[0], true)]);
+        assert_eq!(pruned.hash(), trie.hash());
+        assert_delete(&trie, &pruned, &keys[0]);
+
+        // the branch under the extension is collapsed into the leaf of keys[2],
+        // the extension is merged into the leaf
+        let pruned = prune_trie(&trie, &[PruneKey::new(&keys[1], true)]);
+        assert_eq!(pruned.hash(), trie.hash());
+        assert_delete(&trie, &pruned, &keys[1]);
+
+        // the kept keys are still there after the deletion
+        let pruned = prune_trie(
+            &trie,
+            &[
+                PruneKey::new(&keys[1], true),
+                PruneKey::new(&keys[2], false),
+            ],
+        );
+        let mut deleted = pruned.clone();
+        deleted.delete(&keys[1]).unwrap();
+        assert_eq!(deleted.get(&keys[2]).unwrap(), Some(&[3u8; 32][..]));
+    }
+}
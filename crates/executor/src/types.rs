@@ -1,12 +1,14 @@
 use raiko_lib::primitives::mpt::{self, Error as MptError};
 use reth_evm::execute::{BlockExecutionError, BlockValidationError};
-use reth_primitives::{Address, B256, U256};
+use reth_primitives::{Address, Bloom, B256, U256};
 
 base::stack_error! {
     name: DataProviderError,
     stack_name: DataProviderErrorStack,
     error: {
         UnsupportChainId(u64, Vec<u64>),
+        IncompleteWitness(Vec<MissingWitness>),
+        BuildWitness(String),
     },
     wrap: {
         MptError(MptError),
@@ -17,12 +19,40 @@ base::stack_error! {
 
 pub type DataProviderResult<T> = Result<T, DataProviderError>;
 
+// the pieces of the witness that the block requires but the provider can't serve
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MissingWitness {
+    StateRoot {
+        want: B256,
+        got: B256,
+    },
+    Account(Address),
+    StorageTrie(Address),
+    StorageRoot {
+        addr: Address,
+        want: B256,
+        got: B256,
+    },
+    Code {
+        addr: Address,
+        code_hash: B256,
+    },
+    BlockHash(u64),
+}
+
 base::stack_error! {
     name: ExecutionError,
     stack_name: ExecutionErrorStack,
     error: {
+        EmptyBlocks,
         NotAllTransactionExecuted { remote: Vec<usize>, local: Vec<usize> },
         StateRootMismatch{ remote: B256, local: B256 },
+        ReceiptsRootMismatch{ remote: B256, local: B256 },
+        LogsBloomMismatch{ remote: Bloom, local: Bloom },
+        GasUsedMismatch{ remote: u64, local: u64 },
+        TransactionsRootMismatch{ remote: B256, local: B256 },
+        WithdrawalsRootMismatch{ remote: Option<B256>, local: Option<B256> },
+        ParentHashMismatch{ remote: B256, local: B256 },
     },
     wrap: {
         DataProvider(DataProviderError),
@@ -37,7 +67,26 @@ base::stack_error! {
         SetAccount(addr: Address),
         ApplyChanges(),
         ExecuteBlock(),
+        Block(number: u64),
     }
 }
 
 pub type ExecutionResult<T> = Result<T, ExecutionError>;
+
+base::stack_error! {
+    name: ChainConfigError,
+    stack_name: ChainConfigErrorStack,
+    error: {
+        UnknownHardfork(String),
+        UnknownBaseChain(u64),
+        DuplicateChain(u64),
+    },
+    wrap: {
+        Io(std::io::Error),
+        Json(serde_json::Error),
+    },
+    stack: {
+        ReadConfig(path: String),
+        Chain(chain_id: u64),
+    }
+}
@@ -0,0 +1,60 @@
+use std::{path::PathBuf, sync::Arc};
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use executor::BlockExecutor;
+use prover::{Pob, ProofRequest};
+
+const PROOF_REQUESTS: &[&str] = &[
+    "proof-request-taiko-a7-848185.json",
+    "proof-request-unifi-testnet-48.json",
+];
+
+fn load_pob(name: &str) -> Arc<Pob> {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("../../testdata")
+        .join(name);
+    let data = std::fs::read(&path).unwrap();
+    let req: ProofRequest = serde_json::from_slice(&data).unwrap();
+    Arc::new(req.input.try_into().unwrap())
+}
+
+fn bench_execute(c: &mut Criterion) {
+    let mut group = c.benchmark_group("execute");
+    for name in PROOF_REQUESTS {
+        let pob = load_pob(name);
+        for cache in [false, true] {
+            let id = format!("{}/cache={}", name, cache);
+            group.bench_function(id, |b| {
+                b.iter(|| {
+                    BlockExecutor::new(pob.clone())
+                        .cache(cache)
+                        .execute()
+                        .unwrap()
+                })
+            });
+        }
+    }
+    group.finish();
+}
+
+fn bench_parallel_storage(c: &mut Criterion) {
+    let mut group = c.benchmark_group("parallel_storage");
+    for name in PROOF_REQUESTS {
+        let pob = load_pob(name);
+        for parallel in [false, true] {
+            let id = format!("{}/parallel={}", name, parallel);
+            group.bench_function(id, |b| {
+                b.iter(|| {
+                    BlockExecutor::new(pob.clone())
+                        .parallel(parallel)
+                        .execute()
+                        .unwrap()
+                })
+            });
+        }
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_execute, bench_parallel_storage);
+criterion_main!(benches);
@@ -0,0 +1,236 @@
+use alloy_primitives::address;
+use alloy_sol_types::SolCall;
+use base::stack_error;
+use reth_primitives::{Address, Block, B256};
+
+use crate::{BlockMetaDataFork, PobData};
+
+// the only sender allowed to call the anchor of TaikoL2
+pub const GOLDEN_TOUCH_ADDRESS: Address = address!("0000777735367b36bC9B61C50022d9D0700dB4Ec");
+
+alloy_sol_types::sol! {
+    struct AnchorBaseFeeConfig {
+        uint8 adjustmentQuotient;
+        uint8 sharingPctg;
+        uint32 gasIssuancePerSecond;
+        uint64 minGasExcess;
+        uint32 maxGasIssuancePerBlock;
+    }
+
+    function anchor(
+        bytes32 l1BlockHash,
+        bytes32 l1StateRoot,
+        uint64 l1BlockId,
+        uint32 parentGasUsed
+    ) external;
+
+    function anchorV2(
+        uint64 anchorBlockId,
+        bytes32 anchorStateRoot,
+        uint32 parentGasUsed,
+        AnchorBaseFeeConfig baseFeeConfig
+    ) external;
+}
+
+stack_error! {
+    name: AnchorError,
+    stack_name: AnchorErrorStack,
+    error: {
+        MissingAnchorTx,
+        UnknownL2Contract,
+        InvalidSignature,
+        SenderMismatch{ want: Address, got: Address },
+        ContractMismatch{ want: Address, got: Option<Address> },
+        L1BlockHashMismatch{ want: B256, got: B256 },
+        L1StateRootMismatch{ want: B256, got: B256 },
+        L1BlockIdMismatch{ want: u64, got: u64 },
+        ParentGasUsedMismatch{ want: u64, got: u64 },
+        BaseFeeConfigMismatch,
+    },
+    wrap: {
+        Abi(alloy_sol_types::Error),
+    },
+    stack: {
+        DecodeAnchor(),
+        DecodeAnchorV2(),
+    }
+}
+
+// the first tx of a taiko block must be the anchor call made by the golden touch
+// address, and its arguments must match the l1 data the proof is signed for.
+pub fn verify_anchor(block: &Block, data: &PobData) -> Result<(), AnchorError> {
+    // not a taiko block
+    if let BlockMetaDataFork::None = data.block_meta {
+        return Ok(());
+    }
+
+    let tx = block.body.first().ok_or(AnchorError::MissingAnchorTx)?;
+    let l2_contract = data.l2_contract().ok_or(AnchorError::UnknownL2Contract)?;
+    if tx.to() != Some(l2_contract) {
+        return Err(AnchorError::ContractMismatch {
+            want: l2_contract,
+            got: tx.to(),
+        });
+    }
+    let sender = tx.recover_signer().ok_or(AnchorError::InvalidSignature)?;
+    if sender != GOLDEN_TOUCH_ADDRESS {
+        return Err(AnchorError::SenderMismatch {
+            want: GOLDEN_TOUCH_ADDRESS,
+            got: sender,
+        });
+    }
+
+    let l1_header = &data.l1_header;
+    let parent_gas_used = data.l2_parent_header.gas_used;
+    let (l1_block_id, l1_state_root, anchor_gas_used) = match &data.block_meta {
+        BlockMetaDataFork::None => return Ok(()),
+        BlockMetaDataFork::Hekla(_) => {
+            let call =
+                anchorCall::abi_decode(tx.input(), true).map_err(AnchorError::DecodeAnchor())?;
+            let l1_hash = l1_header.hash_slow();
+            if call.l1BlockHash != l1_hash {
+                return Err(AnchorError::L1BlockHashMismatch {
+                    want: l1_hash,
+                    got: call.l1BlockHash,
+                });
+            }
+            (call.l1BlockId, call.l1StateRoot, call.parentGasUsed)
+        }
+        BlockMetaDataFork::Ontake(_) => {
+            let call =
+                anchorV2Call::abi_decode(tx.input(), true).map_err(AnchorError::DecodeAnchorV2())?;
+            let want = &data.base_fee_config;
+            let got = &call.baseFeeConfig;
+            if want.adjustmentQuotient != got.adjustmentQuotient
+                || want.sharingPctg != got.sharingPctg
+                || want.gasIssuancePerSecond != got.gasIssuancePerSecond
+                || want.minGasExcess != got.minGasExcess
+                || want.maxGasIssuancePerBlock != got.maxGasIssuancePerBlock
+            {
+                return Err(AnchorError::BaseFeeConfigMismatch);
+            }
+            (call.anchorBlockId, call.anchorStateRoot, call.parentGasUsed)
+        }
+    };
+
+    if l1_block_id != l1_header.number {
+        return Err(AnchorError::L1BlockIdMismatch {
+            want: l1_header.number,
+            got: l1_block_id,
+        });
+    }
+    if l1_state_root != l1_header.state_root {
+        return Err(AnchorError::L1StateRootMismatch {
+            want: l1_header.state_root,
+            got: l1_state_root,
+        });
+    }
+    if u64::from(anchor_gas_used) != parent_gas_used {
+        return Err(AnchorError::ParentGasUsedMismatch {
+            want: parent_gas_used,
+            got: anchor_gas_used.into(),
+        });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use reth_primitives::{sign_message, TransactionSigned};
+
+    use super::*;
+    use crate::{testutil::load_request, Pob};
+
+    // an ontake block, its first tx is the anchorV2 call
+    fn load_pob() -> Pob {
+        Pob::try_from(load_request().input).unwrap()
+    }
+
+    #[test]
+    fn test_verify_anchor() {
+        let pob = load_pob();
+        assert!(matches!(pob.data.block_meta, BlockMetaDataFork::Ontake(_)));
+        verify_anchor(&pob.block, &pob.data).unwrap();
+    }
+
+    #[test]
+    fn test_verify_anchor_missing() {
+        let mut pob = load_pob();
+        pob.block.body.clear();
+        assert!(matches!(
+            verify_anchor(&pob.block, &pob.data),
+            Err(AnchorError::MissingAnchorTx)
+        ));
+    }
+
+    #[test]
+    fn test_verify_anchor_sender() {
+        let mut pob = load_pob();
+        let tx = pob.block.body[0].clone();
+        let signature = sign_message(B256::with_last_byte(1), tx.signature_hash()).unwrap();
+        pob.block.body[0] =
+            TransactionSigned::from_transaction_and_signature(tx.transaction, signature);
+        assert!(matches!(
+            verify_anchor(&pob.block, &pob.data),
+            Err(AnchorError::SenderMismatch {
+                want: GOLDEN_TOUCH_ADDRESS,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn test_verify_anchor_contract() {
+        let mut pob = load_pob();
+        let l2_contract = pob.data.l2_contract().unwrap();
+        pob.data.l2_contract = Some(Address::with_last_byte(1));
+        assert!(matches!(
+            verify_anchor(&pob.block, &pob.data),
+            Err(AnchorError::ContractMismatch { got, .. }) if got == Some(l2_contract)
+        ));
+    }
+
+    #[test]
+    fn test_verify_anchor_fork() {
+        // the anchorV2 call can't be taken as the anchor of a hekla block
+        let mut pob = load_pob();
+        pob.data.block_meta = BlockMetaDataFork::Hekla(Default::default());
+        assert!(verify_anchor(&pob.block, &pob.data).is_err());
+
+        // not a taiko block, nothing to verify
+        pob.data.block_meta = BlockMetaDataFork::None;
+        pob.block.body.clear();
+        verify_anchor(&pob.block, &pob.data).unwrap();
+    }
+
+    #[test]
+    fn test_verify_anchor_args() {
+        let mut pob = load_pob();
+        pob.data.l1_header.state_root = B256::with_last_byte(1);
+        assert!(matches!(
+            verify_anchor(&pob.block, &pob.data),
+            Err(AnchorError::L1StateRootMismatch { .. })
+        ));
+
+        let mut pob = load_pob();
+        pob.data.l1_header.number += 1;
+        assert!(matches!(
+            verify_anchor(&pob.block, &pob.data),
+            Err(AnchorError::L1BlockIdMismatch { .. })
+        ));
+
+        let mut pob = load_pob();
+        pob.data.l2_parent_header.gas_used += 1;
+        assert!(matches!(
+            verify_anchor(&pob.block, &pob.data),
+            Err(AnchorError::ParentGasUsedMismatch { .. })
+        ));
+
+        let mut pob = load_pob();
+        pob.data.base_fee_config.sharingPctg ^= 1;
+        assert!(matches!(
+            verify_anchor(&pob.block, &pob.data),
+            Err(AnchorError::BaseFeeConfigMismatch)
+        ));
+    }
+}
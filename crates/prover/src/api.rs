@@ -1,4 +1,6 @@
+use executor::{BlockTrace, ExecutionReport, StateDiff, TracerKind};
 use jsonrpsee::{core::RpcResult, proc_macros::rpc};
+use raiko_core::interfaces::ProofRequest as RpcProofRequest;
 pub use raiko_lib::input::GuestInput;
 use raiko_lib::{
     consts::ChainSpec,
@@ -7,26 +9,76 @@ use raiko_lib::{
 };
 use reth_primitives::{
     revm_primitives::{Address, Bytes, HashMap},
-    Block, Header,
+    Block, Header, B256,
 };
 use serde::{Deserialize, Serialize};
 
-use crate::{guest_input_to_proof_input, BlockMetaDataFork};
+use crate::{
+    guest_input_to_proof_input, BlockMetaDataFork, RpcMultiProofRequest, SignedPoe, TxList,
+};
 
 #[rpc(server, client, namespace = "prover")]
 pub trait ProverV1Api {
     #[method(name = "genProof")]
     async fn gen_proof(&self, req: ProofRequest) -> RpcResult<ProofResponse>;
+
+    // one proof for the contiguous blocks, see `MultiProofRequest`
+    #[method(name = "genMultiProof")]
+    async fn gen_multi_proof(&self, req: GenMultiProofRequest) -> RpcResult<ProofResponse>;
+
+    // fetch the input by raiko and prove it, packed the same as /v1/get_proof by default
+    #[method(name = "getProof")]
+    async fn get_block_proof(
+        &self,
+        req: RpcProofRequest,
+        version: Option<u64>,
+    ) -> RpcResult<ProofResponse>;
+
+    #[method(name = "traceBlock")]
+    async fn trace_block(&self, req: TraceRequest) -> RpcResult<TraceResponse>;
+
+    #[method(name = "stateDiff")]
+    async fn state_diff(&self, req: ProofRequest) -> RpcResult<StateDiffResponse>;
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ProofRequest {
     pub input: ProofInput,
+    // the encoding of the response by its version, see `ProofEncoding`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<u64>,
 }
 
+// contiguous blocks of one chain in ascending order with one witness. the blocks
+// are executed in sequence and a single SignedPoe is returned for all of them:
+// * poe.parent_hash is the parent of the first block
+// * poe.block_hash and poe.state_root are the ones of the last block
+// * poe.graffiti, the prover and the meta hash in the signed message are the ones
+//   of the last block
+// so the proof is verified as the proof of the last block, the state transition
+// from the parent of the first block.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MultiProofRequest {
-    pub input: Vec<ProofInput>,
+    pub input: MultiProofInput,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<u64>,
+}
+
+// the inputs, or the block range [start_block, end_block] to fetch the inputs by raiko
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum GenMultiProofRequest {
+    Inputs(MultiProofRequest),
+    Range(RpcMultiProofRequest),
+}
+
+impl GenMultiProofRequest {
+    pub fn version(&self) -> Option<u64> {
+        match self {
+            Self::Inputs(req) => req.version,
+            Self::Range(req) => req.version,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -41,6 +93,26 @@ pub struct ProofInput {
     pub taiko: ProofTaikoInput,
 }
 
+// the blocks of a MultiProofRequest, the witness is the same as the one of a
+// ProofInput but covers every block: it's taken against the parent of the first
+// block, the nodes required by the later blocks are resolved in it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MultiProofInput {
+    pub blocks: Vec<MultiBlockInput>,
+    pub parent_header: Header,
+    pub chain_spec: ChainSpec,
+    pub parent_state_trie: MptNode,
+    pub parent_storage: HashMap<Address, StorageEntry>,
+    pub contracts: Vec<Bytes>,
+    pub ancestor_headers: Vec<Header>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MultiBlockInput {
+    pub l2_block: Block,
+    pub taiko: ProofTaikoInput,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ProofTaikoInput {
     // Synced L1 header
@@ -49,6 +121,10 @@ pub struct ProofTaikoInput {
     pub metadata: BlockMetaDataFork,
     // Taiko prover data
     pub prover_data: TaikoProverData,
+    // the tx list committed by the metadata, the block body is checked against it.
+    // a taiko block without it is only signed if the prover allows it.
+    #[serde(default)]
+    pub tx_list: Option<TxList>,
 }
 
 impl ProofInput {
@@ -67,6 +143,33 @@ pub fn read_guest_input(data: &[u8]) -> Result<GuestInput, serde_json::Error> {
 pub struct ProofResponse {
     pub version: u64,
     pub data: Bytes,
+    // the SignedPoe of the json encoding, `data` is empty then
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signed_poe: Option<SignedPoe>,
+    // the metrics of the execution, not covered by the signature
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub report: Option<ExecutionReport>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StateDiffResponse {
+    pub block_hash: B256,
+    pub state_root: B256,
+    pub diff: StateDiff,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TraceRequest {
+    pub input: ProofInput,
+    pub tracer: TracerKind,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TraceResponse {
+    pub traces: Vec<BlockTrace>,
+    // the execution error if any, the traces are collected up to the failure
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
 }
 
 #[cfg(test)]
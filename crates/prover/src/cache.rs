@@ -0,0 +1,225 @@
+use std::{
+    path::Path,
+    sync::{Arc, Mutex},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use base::stack_error;
+use executor::ExecutionReport;
+use reth_primitives::{Address, B256, U256};
+use rusqlite::{params, Connection, OptionalExtension};
+
+use crate::{meta_hash, PobData, ProofInput, SignedPoe};
+
+stack_error! {
+    name: ProofCacheError,
+    stack_name: ProofCacheErrorStack,
+    error: {},
+    wrap: {
+        Sqlite(rusqlite::Error),
+        Json(serde_json::Error),
+    },
+    stack: {
+        Open(path: String),
+    }
+}
+
+// everything the signature commits to besides the instance, the same block
+// proposed with another metadata or for another prover is proved again.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProofCacheKey {
+    pub chain_id: u64,
+    pub block_hash: B256,
+    pub meta_hash: B256,
+    pub prover: Address,
+    pub graffiti: B256,
+}
+
+impl ProofCacheKey {
+    // the block hash is the one claimed by the input, only the proof of an
+    // executed block with the same hash is ever stored.
+    pub fn from_input(input: &ProofInput) -> Self {
+        Self {
+            chain_id: input.chain_spec.chain_id,
+            block_hash: input.l2_block.hash_slow(),
+            meta_hash: meta_hash(&input.taiko.metadata),
+            prover: input.taiko.prover_data.prover,
+            graffiti: input.taiko.prover_data.graffiti,
+        }
+    }
+
+    pub fn from_pob_data(data: &PobData, block_hash: B256) -> Self {
+        Self {
+            chain_id: data.chain_id,
+            block_hash,
+            meta_hash: meta_hash(&data.block_meta),
+            prover: data.prover,
+            graffiti: data.graffiti,
+        }
+    }
+}
+
+// the signed proofs by block, scoped to the signing instance. once the key is
+// rotated the proofs of the retired instance are purged, as the registry no
+// longer accepts them.
+#[derive(Clone)]
+pub struct ProofCache {
+    conn: Arc<Mutex<Connection>>,
+}
+
+impl ProofCache {
+    pub fn open(path: &str) -> Result<Self, ProofCacheError> {
+        let conn =
+            Connection::open(Path::new(path)).map_err(ProofCacheError::Open(&path.to_owned()))?;
+        Self::new(conn)
+    }
+
+    pub fn in_memory() -> Result<Self, ProofCacheError> {
+        Self::new(Connection::open_in_memory()?)
+    }
+
+    fn new(conn: Connection) -> Result<Self, ProofCacheError> {
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS proofs (
+                chain_id INTEGER NOT NULL,
+                block_hash BLOB NOT NULL,
+                meta_hash BLOB NOT NULL,
+                prover BLOB NOT NULL,
+                graffiti BLOB NOT NULL,
+                instance_id BLOB NOT NULL,
+                instance BLOB NOT NULL,
+                signed_poe TEXT NOT NULL,
+                report TEXT NOT NULL,
+                created_at INTEGER NOT NULL,
+                PRIMARY KEY (chain_id, block_hash, meta_hash, prover, graffiti, instance_id, instance)
+            );",
+        )?;
+        Ok(Self {
+            conn: Arc::new(Mutex::new(conn)),
+        })
+    }
+
+    pub fn get(
+        &self,
+        key: &ProofCacheKey,
+        instance_id: U256,
+        instance: Address,
+    ) -> Result<Option<(SignedPoe, ExecutionReport)>, ProofCacheError> {
+        let conn = self.conn.lock().unwrap();
+        Self::purge_retired(&conn, instance_id, instance)?;
+        let row = conn
+            .query_row(
+                "SELECT signed_poe, report FROM proofs
+                WHERE chain_id = ?1 AND block_hash = ?2 AND meta_hash = ?3 AND prover = ?4
+                AND graffiti = ?5 AND instance_id = ?6 AND instance = ?7",
+                params![
+                    key.chain_id,
+                    key.block_hash.as_slice(),
+                    key.meta_hash.as_slice(),
+                    key.prover.as_slice(),
+                    key.graffiti.as_slice(),
+                    instance_id.to_be_bytes::<32>(),
+                    instance.as_slice(),
+                ],
+                |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)),
+            )
+            .optional()?;
+        let Some((signed_poe, report)) = row else {
+            return Ok(None);
+        };
+        Ok(Some((
+            serde_json::from_str(&signed_poe)?,
+            serde_json::from_str(&report)?,
+        )))
+    }
+
+    pub fn put(
+        &self,
+        key: &ProofCacheKey,
+        signed_poe: &SignedPoe,
+        report: &ExecutionReport,
+    ) -> Result<(), ProofCacheError> {
+        let conn = self.conn.lock().unwrap();
+        Self::purge_retired(&conn, signed_poe.id, signed_poe.new_instance)?;
+        let created_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        conn.execute(
+            "INSERT OR REPLACE INTO proofs VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+            params![
+                key.chain_id,
+                key.block_hash.as_slice(),
+                key.meta_hash.as_slice(),
+                key.prover.as_slice(),
+                key.graffiti.as_slice(),
+                signed_poe.id.to_be_bytes::<32>(),
+                signed_poe.new_instance.as_slice(),
+                serde_json::to_string(signed_poe)?,
+                serde_json::to_string(report)?,
+                created_at,
+            ],
+        )?;
+        Ok(())
+    }
+
+    fn purge_retired(
+        conn: &Connection,
+        instance_id: U256,
+        instance: Address,
+    ) -> Result<(), ProofCacheError> {
+        let n = conn.execute(
+            "DELETE FROM proofs WHERE instance_id != ?1 OR instance != ?2",
+            params![instance_id.to_be_bytes::<32>(), instance.as_slice()],
+        )?;
+        if n > 0 {
+            log::info!("purged {} cached proofs of the retired instances", n);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Poe;
+
+    fn signed_poe(id: u64, instance: Address) -> SignedPoe {
+        SignedPoe {
+            poe: Poe::default(),
+            id: U256::from(id),
+            new_instance: instance,
+            signature: vec![1u8; 65].into(),
+            teeType: U256::from(1),
+        }
+    }
+
+    #[test]
+    fn test_proof_cache_key_rotation() {
+        let cache = ProofCache::in_memory().unwrap();
+        let key = ProofCacheKey {
+            chain_id: 167009,
+            block_hash: B256::repeat_byte(1),
+            meta_hash: B256::repeat_byte(2),
+            prover: Address::repeat_byte(3),
+            graffiti: B256::ZERO,
+        };
+        let old = Address::repeat_byte(4);
+        let new = Address::repeat_byte(5);
+        cache
+            .put(&key, &signed_poe(1, old), &ExecutionReport::default())
+            .unwrap();
+
+        let (poe, _) = cache.get(&key, U256::from(1), old).unwrap().unwrap();
+        assert_eq!(poe.new_instance, old);
+        let other = ProofCacheKey {
+            graffiti: B256::repeat_byte(6),
+            ..key.clone()
+        };
+        assert!(cache.get(&other, U256::from(1), old).unwrap().is_none());
+
+        // rotated, the proof of the retired instance is gone for good
+        assert!(cache.get(&key, U256::from(2), new).unwrap().is_none());
+        assert!(cache.get(&key, U256::from(1), old).unwrap().is_none());
+    }
+}
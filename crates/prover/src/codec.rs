@@ -0,0 +1,89 @@
+use std::io::Write;
+
+use base::stack_error;
+use flate2::{write::GzEncoder, Compression};
+use serde::{Deserialize, Serialize};
+
+use crate::{ProofInput, ProofRequest};
+
+stack_error! {
+    name: CodecError,
+    stack_name: CodecErrorStack,
+    error: {
+        UnsupportedContentType(String),
+    },
+    wrap: {
+        Io(std::io::Error),
+        Json(serde_json::Error),
+        Bincode(bincode::Error),
+    },
+    stack: {}
+}
+
+pub const CONTENT_TYPE_JSON: &str = "application/json";
+// bincode of the ProofRequest, the mpt nodes and the codes are carried as raw bytes
+pub const CONTENT_TYPE_BINCODE: &str = "application/x-bincode";
+
+// bincode can't skip the fields by `skip_serializing_if`, so the request is
+// carried with all of its fields
+#[derive(Deserialize)]
+struct BincodeProofRequest {
+    input: ProofInput,
+    version: Option<u64>,
+}
+
+#[derive(Serialize)]
+struct BincodeProofRequestRef<'a> {
+    input: &'a ProofInput,
+    version: Option<u64>,
+}
+
+pub fn encode_proof_request_bincode(req: &ProofRequest) -> Result<Vec<u8>, CodecError> {
+    let req = BincodeProofRequestRef {
+        input: &req.input,
+        version: req.version,
+    };
+    Ok(bincode::serialize(&req)?)
+}
+
+// the body is expected to be decompressed by its Content-Encoding already,
+// a missing content type is taken as json.
+pub fn decode_proof_request(content_type: &str, body: &[u8]) -> Result<ProofRequest, CodecError> {
+    match content_type {
+        "" | CONTENT_TYPE_JSON => Ok(serde_json::from_slice(body)?),
+        CONTENT_TYPE_BINCODE => {
+            let req: BincodeProofRequest = bincode::deserialize(body)?;
+            Ok(ProofRequest {
+                input: req.input,
+                version: req.version,
+            })
+        }
+        content_type => Err(CodecError::UnsupportedContentType(content_type.to_owned())),
+    }
+}
+
+// for sending with `Content-Encoding: gzip`
+pub fn gzip(data: &[u8]) -> Result<Vec<u8>, CodecError> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(data)?;
+    Ok(encoder.finish()?)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::testutil::load_request;
+
+    #[test]
+    fn test_bincode_round_trip() {
+        let req = load_request();
+        let encoded = encode_proof_request_bincode(&req).unwrap();
+        assert!(encoded.len() < serde_json::to_vec(&req).unwrap().len());
+
+        let decoded = decode_proof_request(CONTENT_TYPE_BINCODE, &encoded).unwrap();
+        assert_eq!(
+            serde_json::to_value(&decoded).unwrap(),
+            serde_json::to_value(&req).unwrap()
+        );
+    }
+}
@@ -0,0 +1,125 @@
+use std::{
+    io::Write,
+    panic::{catch_unwind, AssertUnwindSafe},
+};
+
+use base::stack_error;
+use raiko_lib::{
+    builder::calculate_block_header,
+    input::{
+        ontake::BlockProposedV2, BlockProposed, BlockProposedFork, GuestInput, TaikoGuestInput,
+    },
+};
+use reth_primitives::{Header, TransactionSigned, B256, U256};
+
+use crate::{BlockMetaDataFork, ProofInput};
+
+stack_error! {
+    name: CrossCheckError,
+    stack_name: CrossCheckErrorStack,
+    error: {
+        EmptyBlockBody,
+        RaikoExecutionFailed{ reason: String },
+        StateRootMismatch{ want: B256, got: B256 },
+        BlockHashMismatch{ want: B256, got: B256 },
+    },
+    wrap: {
+        Io(std::io::Error),
+    },
+    stack: {
+        Block(number: u64),
+    }
+}
+
+// convert the input back to the one consumed by raiko, the txs except the anchor
+// are carried as zlib compressed calldata, so the blob fields are dropped.
+pub fn proof_input_to_guest_input(input: &ProofInput) -> Result<GuestInput, CrossCheckError> {
+    let block = &input.l2_block;
+    let (anchor_tx, txs) = block
+        .body
+        .split_first()
+        .ok_or(CrossCheckError::EmptyBlockBody)?;
+
+    let block_proposed = match &input.taiko.metadata {
+        BlockMetaDataFork::None => BlockProposedFork::Nothing,
+        BlockMetaDataFork::Hekla(meta) => {
+            let mut meta = meta.clone();
+            meta.blobUsed = false;
+            BlockProposedFork::Hekla(BlockProposed {
+                blockId: U256::from(block.number),
+                assignedProver: input.taiko.prover_data.prover,
+                livenessBond: Default::default(),
+                meta,
+                depositsProcessed: Vec::new(),
+            })
+        }
+        BlockMetaDataFork::Ontake(meta) => {
+            let mut meta = meta.clone();
+            meta.blobUsed = false;
+            BlockProposedFork::Ontake(BlockProposedV2 {
+                blockId: U256::from(block.number),
+                meta,
+            })
+        }
+    };
+
+    Ok(GuestInput {
+        block: block.clone(),
+        chain_spec: input.chain_spec.clone(),
+        parent_header: input.parent_header.clone(),
+        parent_state_trie: input.parent_state_trie.clone(),
+        parent_storage: input.parent_storage.clone(),
+        contracts: input.contracts.clone(),
+        ancestor_headers: input.ancestor_headers.clone(),
+        taiko: TaikoGuestInput {
+            l1_header: input.taiko.l1_header.clone(),
+            tx_data: compress_txs(txs)?,
+            anchor_tx: Some(anchor_tx.clone()),
+            block_proposed,
+            prover_data: input.taiko.prover_data.clone(),
+            ..Default::default()
+        },
+    })
+}
+
+fn compress_txs(txs: &[TransactionSigned]) -> Result<Vec<u8>, CrossCheckError> {
+    let data = alloy_rlp::encode(txs.to_vec());
+    let mut encoder = libflate::zlib::Encoder::new(Vec::new())?;
+    encoder.write_all(&data)?;
+    Ok(encoder.finish().into_result()?)
+}
+
+// execute the input with raiko's executor, it must produce the same block as ours
+pub fn cross_check(input: &ProofInput, header: &Header) -> Result<(), CrossCheckError> {
+    let number = input.l2_block.number;
+    cross_check_block(input, header).map_err(CrossCheckError::Block(&number))
+}
+
+fn cross_check_block(input: &ProofInput, header: &Header) -> Result<(), CrossCheckError> {
+    let guest_input = proof_input_to_guest_input(input)?;
+
+    // raiko panics on any execution failure
+    let raiko_header = catch_unwind(AssertUnwindSafe(|| calculate_block_header(&guest_input)))
+        .map_err(|err| {
+            let reason = match err.downcast_ref::<&str>() {
+                Some(n) => n.to_string(),
+                None => match err.downcast_ref::<String>() {
+                    Some(n) => n.clone(),
+                    None => "unknown panic".to_owned(),
+                },
+            };
+            CrossCheckError::RaikoExecutionFailed { reason }
+        })?;
+
+    if raiko_header.state_root != header.state_root {
+        return Err(CrossCheckError::StateRootMismatch {
+            want: header.state_root,
+            got: raiko_header.state_root,
+        });
+    }
+    let (want, got) = (header.hash_slow(), raiko_header.hash_slow());
+    if want != got {
+        return Err(CrossCheckError::BlockHashMismatch { want, got });
+    }
+    Ok(())
+}
@@ -0,0 +1,170 @@
+use alloy_sol_types::SolValue;
+use executor::ExecutionReport;
+use jsonrpsee::types::ErrorObject;
+use reth_primitives::Bytes;
+
+use crate::{ProofResponse, SignedPoe};
+
+// the encodings of ProofResponse.data, the version of the response names the one
+// used and the request asks for one by it:
+// * 1: id[4] ++ new_instance[20] ++ signature[65]
+// * 2: abi encoded SignedPoe, as accepted by ProverRegistryVerifier
+// * 3: the SignedPoe with all the fields of the poe as `ProofResponse.signed_poe`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProofEncoding {
+    Packed,
+    Abi,
+    Json,
+}
+
+impl ProofEncoding {
+    pub fn version(&self) -> u64 {
+        match self {
+            Self::Packed => 1,
+            Self::Abi => 2,
+            Self::Json => 3,
+        }
+    }
+
+    // the encoding requested by the version, `default` if it's not specified
+    pub fn select(version: Option<u64>, default: Self) -> Result<Self, ErrorObject<'static>> {
+        match version {
+            Some(version) => {
+                Self::try_from(version).map_err(|err| ErrorObject::owned(14001, err, None::<()>))
+            }
+            None => Ok(default),
+        }
+    }
+
+    // the json encoding leaves the data empty, see `response`
+    pub fn encode(&self, signed_poe: &SignedPoe) -> Bytes {
+        match self {
+            Self::Packed => {
+                let id_be_bytes: [u8; 32] = signed_poe.id.to_be_bytes::<32>();
+                let new_instance: [u8; 20] = signed_poe.new_instance.into_array();
+                [
+                    &id_be_bytes[28..],
+                    &new_instance[..],
+                    &signed_poe.signature[..],
+                ]
+                .concat()
+                .into()
+            }
+            Self::Abi => signed_poe.abi_encode().into(),
+            Self::Json => Bytes::new(),
+        }
+    }
+
+    pub fn response(&self, signed_poe: &SignedPoe, report: ExecutionReport) -> ProofResponse {
+        ProofResponse {
+            version: self.version(),
+            data: self.encode(signed_poe),
+            signed_poe: match self {
+                Self::Json => Some(signed_poe.clone()),
+                _ => None,
+            },
+            report: Some(report),
+        }
+    }
+}
+
+impl TryFrom<u64> for ProofEncoding {
+    type Error = String;
+
+    fn try_from(version: u64) -> Result<Self, Self::Error> {
+        match version {
+            1 => Ok(Self::Packed),
+            2 => Ok(Self::Abi),
+            3 => Ok(Self::Json),
+            _ => Err(format!("unsupported proof version: {}", version)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use reth_primitives::{Address, B256, U256};
+
+    use super::*;
+    use crate::Poe;
+
+    fn signed_poe() -> SignedPoe {
+        SignedPoe {
+            poe: Poe {
+                parent_hash: B256::repeat_byte(1),
+                block_hash: B256::repeat_byte(2),
+                state_root: B256::repeat_byte(3),
+                graffiti: B256::repeat_byte(4),
+            },
+            id: U256::from(0x01020304),
+            new_instance: Address::repeat_byte(5),
+            signature: (0..65).collect::<Vec<u8>>().into(),
+            teeType: U256::from(1),
+        }
+    }
+
+    // the packed response before the encodings were selectable
+    fn packed_proof_response(signed_poe: SignedPoe) -> Vec<u8> {
+        let id_be_bytes: [u8; 32] = signed_poe.id.to_be_bytes::<32>();
+        let id: [u8; 4] = id_be_bytes[28..].try_into().unwrap();
+        let new_instance: [u8; 20] = signed_poe.new_instance.into_array();
+        let signature: [u8; 65] = signed_poe.signature.to_vec().try_into().unwrap();
+        let data: [u8; 89] = [&id[..], &new_instance[..], &signature[..]]
+            .concat()
+            .try_into()
+            .unwrap();
+        data.to_vec()
+    }
+
+    #[test]
+    fn test_packed() {
+        let response = ProofEncoding::Packed.response(&signed_poe(), ExecutionReport::default());
+        assert_eq!(response.version, 1);
+        assert_eq!(response.data.to_vec(), packed_proof_response(signed_poe()));
+        assert!(response.signed_poe.is_none());
+    }
+
+    #[test]
+    fn test_abi() {
+        let response = ProofEncoding::Abi.response(&signed_poe(), ExecutionReport::default());
+        assert_eq!(response.version, 2);
+        let decoded = SignedPoe::abi_decode(&response.data, true).unwrap();
+        assert_eq!(
+            serde_json::to_value(decoded).unwrap(),
+            serde_json::to_value(signed_poe()).unwrap()
+        );
+        assert!(response.signed_poe.is_none());
+    }
+
+    #[test]
+    fn test_json() {
+        let response = ProofEncoding::Json.response(&signed_poe(), ExecutionReport::default());
+        assert_eq!(response.version, 3);
+        assert!(response.data.is_empty());
+
+        // the poe is a structured field of the response
+        let value = serde_json::to_value(&response).unwrap();
+        assert!(value["signed_poe"]["poe"].is_object());
+        assert_eq!(
+            value["signed_poe"],
+            serde_json::to_value(signed_poe()).unwrap()
+        );
+        let decoded: ProofResponse = serde_json::from_value(value).unwrap();
+        assert_eq!(
+            serde_json::to_value(decoded.signed_poe).unwrap(),
+            serde_json::to_value(signed_poe()).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_select() {
+        let select = |version| ProofEncoding::select(version, ProofEncoding::Abi);
+        assert_eq!(select(None).unwrap(), ProofEncoding::Abi);
+        assert_eq!(select(Some(1)).unwrap(), ProofEncoding::Packed);
+        assert_eq!(select(Some(2)).unwrap(), ProofEncoding::Abi);
+        assert_eq!(select(Some(3)).unwrap(), ProofEncoding::Json);
+        for version in [0, 4, u64::MAX] {
+            assert_eq!(select(Some(version)).unwrap_err().code(), 14001);
+        }
+    }
+}
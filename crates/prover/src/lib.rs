@@ -1,11 +1,41 @@
 mod api;
 pub use api::*;
 
+mod anchor;
+pub use anchor::*;
+
+mod tx_list;
+pub use tx_list::*;
+
 mod pob;
 pub use pob::*;
 
+mod multi_pob;
+pub use multi_pob::*;
+
+mod rpc_provider;
+pub use rpc_provider::*;
+
+mod cross_check;
+pub use cross_check::*;
+
 mod poe;
 pub use poe::*;
 
+mod verify;
+pub use verify::*;
+
+mod encoding;
+pub use encoding::*;
+
+mod codec;
+pub use codec::*;
+
+mod cache;
+pub use cache::*;
+
 mod prove;
-pub use prove::*;
\ No newline at end of file
+pub use prove::*;
+
+#[cfg(test)]
+mod testutil;
@@ -0,0 +1,268 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use executor::{
+    collect_nodes, resolve_nodes, BlockDataProvider, DataProviderError, DataProviderResult,
+};
+use raiko_lib::{input::ontake::BaseFeeConfig, primitives::mpt::MptNode};
+use reth_evm::execute::ProviderError;
+use reth_evm_ethereum::taiko::TaikoData;
+use reth_primitives::{keccak256, Address, Block, BlockWithSenders, Bytes, Header, B256, U256};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    get_base_fee_config, verify_ancestor_headers, verify_anchor, verify_l1_header, verify_tx_list,
+    BlockMetaDataFork, MultiBlockInput, MultiProofInput, PobData, PobError, ProofInput, ProveError,
+};
+
+// Pob for a range of consecutive blocks, all the blocks share one witness
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct MultiPob {
+    pub blocks: Vec<Block>,
+    // taiko data of each block, in the same order as `blocks`
+    pub taiko: Vec<PobTaikoData>,
+    // the witness against the parent of the first block,
+    // l2_parent_header is the parent of the first block,
+    // the other taiko fields are the ones of the last block
+    pub data: PobData,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct PobTaikoData {
+    pub l1_header: Header,
+    pub block_meta: BlockMetaDataFork,
+    pub base_fee_config: BaseFeeConfig,
+}
+
+impl MultiProofInput {
+    // build the shared witness from the inputs of each block, as raiko fetches them.
+    //
+    // the witness of the next block is built on top of the post state of the current block,
+    // the nodes which are not modified by the current block have the same hash in both of
+    // them, so they can be used to resolve the digests of the current witness. the
+    // ancestors of the first block cover the BLOCKHASH window of the later ones.
+    pub fn from_inputs(inputs: Vec<ProofInput>) -> Result<Self, ProveError> {
+        let mut inputs = inputs.into_iter();
+        let first = inputs.next().ok_or(ProveError::EmptyProofInputs)?;
+        let mut multi = Self {
+            blocks: vec![MultiBlockInput {
+                l2_block: first.l2_block,
+                taiko: first.taiko,
+            }],
+            parent_header: first.parent_header,
+            chain_spec: first.chain_spec,
+            parent_state_trie: first.parent_state_trie,
+            parent_storage: first.parent_storage,
+            contracts: first.contracts,
+            ancestor_headers: first.ancestor_headers,
+        };
+        for (idx, input) in inputs.enumerate() {
+            if input.chain_spec.chain_id != multi.chain_spec.chain_id {
+                return Err(ProveError::ChainIdMismatch {
+                    idx: idx + 1,
+                    want: multi.chain_spec.chain_id,
+                    got: input.chain_spec.chain_id,
+                });
+            }
+
+            let mut nodes = HashMap::new();
+            collect_nodes(&input.parent_state_trie, &mut nodes);
+            for (storage_trie, _) in input.parent_storage.values() {
+                collect_nodes(storage_trie, &mut nodes);
+            }
+
+            multi.parent_state_trie = resolve_nodes(&multi.parent_state_trie, &nodes);
+            for (addr, (storage_trie, slots)) in input.parent_storage {
+                match multi.parent_storage.get_mut(&addr) {
+                    Some((current, current_slots)) => {
+                        *current = resolve_nodes(current, &nodes);
+                        for slot in slots {
+                            if !current_slots.contains(&slot) {
+                                current_slots.push(slot);
+                            }
+                        }
+                    }
+                    // not touched by the previous blocks, it's still the pre state
+                    None => {
+                        multi.parent_storage.insert(addr, (storage_trie, slots));
+                    }
+                }
+            }
+
+            let codes = multi
+                .contracts
+                .iter()
+                .map(keccak256)
+                .collect::<BTreeSet<_>>();
+            for code in input.contracts {
+                if !codes.contains(&keccak256(&code)) {
+                    multi.contracts.push(code);
+                }
+            }
+
+            multi.blocks.push(MultiBlockInput {
+                l2_block: input.l2_block,
+                taiko: input.taiko,
+            });
+        }
+        Ok(multi)
+    }
+
+    // the first block alone, its witness covers the whole range
+    pub fn first_block_input(&self) -> Option<ProofInput> {
+        let block = self.blocks.first()?;
+        Some(ProofInput {
+            l2_block: block.l2_block.clone(),
+            parent_header: self.parent_header.clone(),
+            chain_spec: self.chain_spec.clone(),
+            parent_state_trie: self.parent_state_trie.clone(),
+            parent_storage: self.parent_storage.clone(),
+            contracts: self.contracts.clone(),
+            ancestor_headers: self.ancestor_headers.clone(),
+            taiko: block.taiko.clone(),
+        })
+    }
+}
+
+impl MultiPob {
+    pub fn from_input(input: MultiProofInput) -> Result<Self, ProveError> {
+        if input.blocks.is_empty() {
+            return Err(ProveError::EmptyProofInputs);
+        }
+        let block_hashes = verify_ancestor_headers(&input.parent_header, &input.ancestor_headers)?;
+
+        let mut storage_mpt_nodes = BTreeMap::new();
+        for (addr, (mpt, _)) in input.parent_storage {
+            storage_mpt_nodes.insert(addr, mpt);
+        }
+
+        let first = &input.blocks[0].taiko;
+        let mut data = PobData {
+            chain_id: input.chain_spec.chain_id,
+            prev_state_root: input.parent_header.state_root,
+            block_hashes,
+            mpt_nodes: input.parent_state_trie,
+            storage_mpt_nodes,
+            codes: input.contracts,
+            l1_header: first.l1_header.clone(),
+            l1_contract: input.chain_spec.l1_contract,
+            l2_contract: input.chain_spec.l2_contract,
+            l2_parent_header: input.parent_header.clone(),
+            graffiti: first.prover_data.graffiti,
+            prover: first.prover_data.prover,
+            base_fee_config: get_base_fee_config(&first.metadata),
+            block_meta: first.metadata.clone(),
+        };
+
+        let mut blocks: Vec<Block> = Vec::with_capacity(input.blocks.len());
+        let mut taiko = Vec::with_capacity(input.blocks.len());
+        for (idx, block) in input.blocks.into_iter().enumerate() {
+            let parent = blocks.last().map_or(&input.parent_header, |n| &n.header);
+            let parent_hash = parent.hash_slow();
+            if block.l2_block.parent_hash != parent_hash {
+                if idx == 0 {
+                    return Err(PobError::ParentHashMismatch {
+                        want: block.l2_block.parent_hash,
+                        got: parent_hash,
+                    }
+                    .into());
+                }
+                return Err(ProveError::NonContiguousBlocks {
+                    idx,
+                    parent_hash: block.l2_block.parent_hash,
+                    prev_hash: parent_hash,
+                });
+            }
+
+            // the taiko fields are checked as the ones of a single block,
+            // they are left with the ones of the last block
+            verify_l1_header(&block.taiko.metadata, &block.taiko.l1_header)?;
+            data.l2_parent_header = parent.clone();
+            data.l1_header = block.taiko.l1_header;
+            data.graffiti = block.taiko.prover_data.graffiti;
+            data.prover = block.taiko.prover_data.prover;
+            data.base_fee_config = get_base_fee_config(&block.taiko.metadata);
+            data.block_meta = block.taiko.metadata;
+            verify_anchor(&block.l2_block, &data).map_err(PobError::from)?;
+            if let Some(tx_list) = &block.taiko.tx_list {
+                verify_tx_list(&block.l2_block, &data, tx_list).map_err(PobError::from)?;
+            }
+
+            taiko.push(PobTaikoData {
+                l1_header: data.l1_header.clone(),
+                block_meta: data.block_meta.clone(),
+                base_fee_config: data.base_fee_config.clone(),
+            });
+            blocks.push(block.l2_block);
+        }
+        data.l2_parent_header = input.parent_header;
+
+        Ok(Self {
+            blocks,
+            taiko,
+            data,
+        })
+    }
+}
+
+impl BlockDataProvider for MultiPob {
+    type ExtData = TaikoData;
+
+    fn ext_data(&self, idx: usize) -> Self::ExtData {
+        let taiko = &self.taiko[idx];
+        let parent_header = match idx {
+            0 => self.data.l2_parent_header.clone(),
+            _ => self.blocks[idx - 1].header.clone(),
+        };
+        TaikoData {
+            l2_contract: self.data.l2_contract().unwrap_or_default(),
+            l1_header: taiko.l1_header.clone(),
+            parent_header,
+            base_fee_config: unsafe { std::mem::transmute(taiko.base_fee_config.clone()) },
+        }
+    }
+
+    fn chain_id(&self) -> u64 {
+        self.data.chain_id
+    }
+
+    fn block_hash(&self, number: u64) -> Result<B256, ProviderError> {
+        self.data.block_hash(number)
+    }
+
+    fn blocks(&self) -> &[Block] {
+        &self.blocks
+    }
+
+    fn contract_codes(&self) -> &[Bytes] {
+        &self.data.codes
+    }
+
+    fn state_trie(&self) -> DataProviderResult<&MptNode> {
+        Ok(&self.data.mpt_nodes)
+    }
+
+    fn storage_state_trie(&self, addr: Address) -> Option<&MptNode> {
+        self.data.storage_mpt_nodes.get(&addr)
+    }
+
+    fn check_witness(&self, blocks: &[BlockWithSenders]) -> DataProviderResult<()> {
+        let missing = self.data.missing_witness(blocks);
+        if !missing.is_empty() {
+            return Err(DataProviderError::IncompleteWitness(missing));
+        }
+        Ok(())
+    }
+
+    fn get_acc<T: alloy_rlp::Decodable>(&self, addr: Address) -> Result<Option<T>, ProviderError> {
+        self.data.get_acc(addr)
+    }
+
+    fn get_slot<T: alloy_rlp::Decodable>(
+        &self,
+        key: Address,
+        root: B256,
+        slot: U256,
+    ) -> Result<Option<T>, ProviderError> {
+        self.data.get_slot(key, root, slot)
+    }
+}
@@ -1,20 +1,46 @@
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
 
 use alloy_sol_types::SolValue;
+use base::stack_error;
 use raiko_lib::{
     input::{
         ontake::{BaseFeeConfig, BlockMetadataV2},
         BlockMetadata, BlockProposedFork, GuestInput,
     },
-    primitives::mpt::MptNode,
+    primitives::mpt::{MptNode, StateAccount},
 };
 use reth_evm::execute::ProviderError;
 use reth_evm_ethereum::taiko::TaikoData;
-use reth_primitives::{keccak256, Address, Block, Bytes, Header, B256, U256};
+use reth_primitives::{
+    constants::EMPTY_ROOT_HASH, keccak256, revm_primitives::KECCAK_EMPTY, Address, Block,
+    BlockWithSenders, Bytes, Header, B256, U256,
+};
 use serde::{Deserialize, Serialize};
 
-use crate::{ProofInput, ProofTaikoInput};
-use executor::BlockDataProvider;
+use crate::{
+    verify_anchor, verify_tx_list, AnchorError, ProofInput, ProofTaikoInput, TxList, TxListError,
+};
+use executor::{
+    get_chain_contracts, prune_trie, AccessedState, BlockDataProvider, DataProviderError,
+    DataProviderResult, MissingWitness, PruneKey,
+};
+
+stack_error! {
+    name: PobError,
+    stack_name: PobErrorStack,
+    error: {
+        AncestorNotContiguous{ number: u64, want: Option<u64> },
+        AncestorHashMismatch{ number: u64, want: B256, got: B256 },
+        ParentHashMismatch{ want: B256, got: B256 },
+        L1HashMismatch{ fork: &'static str, want: B256, got: B256 },
+        L1HeightMismatch{ fork: &'static str, want: u64, got: u64 },
+    },
+    wrap: {
+        Anchor(AnchorError),
+        TxList(TxListError),
+    },
+    stack: {}
+}
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct Pob {
@@ -49,13 +75,294 @@ pub struct PobData {
     pub base_fee_config: BaseFeeConfig,
 }
 
-impl From<ProofInput> for Pob {
-    fn from(value: ProofInput) -> Self {
-        let mut block_hashes = BTreeMap::new();
-        for item in &value.ancestor_headers {
-            block_hashes.insert(item.number, item.hash_slow());
+impl PobData {
+    // collect every account, storage trie, bytecode and block hash that
+    // the blocks require but is missing from the witness
+    pub fn missing_witness(&self, blocks: &[BlockWithSenders]) -> Vec<MissingWitness> {
+        let mut missing = Vec::new();
+
+        let state_root = self.mpt_nodes.hash();
+        if state_root != self.prev_state_root {
+            missing.push(MissingWitness::StateRoot {
+                want: self.prev_state_root,
+                got: state_root,
+            });
         }
-        block_hashes.insert(value.parent_header.number, value.parent_header.hash_slow());
+
+        // the accounts which will be touched for sure
+        let mut addrs = self
+            .storage_mpt_nodes
+            .keys()
+            .cloned()
+            .collect::<BTreeSet<_>>();
+        if let Some(l2_contract) = self.l2_contract() {
+            addrs.insert(l2_contract);
+        }
+        for block in blocks {
+            addrs.insert(block.header.beneficiary);
+            for (sender, tx) in block.transactions_with_sender() {
+                addrs.insert(*sender);
+                if let Some(to) = tx.to() {
+                    addrs.insert(to);
+                }
+            }
+            if let Some(withdrawals) = &block.withdrawals {
+                addrs.extend(withdrawals.iter().map(|n| n.address));
+            }
+        }
+
+        let codes = self.codes.iter().map(keccak256).collect::<BTreeSet<_>>();
+        for addr in addrs {
+            let acc = match self
+                .mpt_nodes
+                .get_rlp::<StateAccount>(keccak256(addr).as_slice())
+            {
+                Ok(acc) => acc,
+                Err(_) => {
+                    missing.push(MissingWitness::Account(addr));
+                    continue;
+                }
+            };
+            let Some(storage_trie) = self.storage_mpt_nodes.get(&addr) else {
+                missing.push(MissingWitness::StorageTrie(addr));
+                continue;
+            };
+            let Some(acc) = acc else {
+                if !storage_trie.is_empty() {
+                    missing.push(MissingWitness::StorageRoot {
+                        addr,
+                        want: EMPTY_ROOT_HASH,
+                        got: storage_trie.hash(),
+                    });
+                }
+                continue;
+            };
+            if storage_trie.hash() != acc.storage_root {
+                missing.push(MissingWitness::StorageRoot {
+                    addr,
+                    want: acc.storage_root,
+                    got: storage_trie.hash(),
+                });
+            }
+            if acc.code_hash != KECCAK_EMPTY && !codes.contains(&acc.code_hash) {
+                missing.push(MissingWitness::Code {
+                    addr,
+                    code_hash: acc.code_hash,
+                });
+            }
+        }
+
+        // the hashes of the blocks inside the range are known after execution
+        if let Some(block) = blocks.first() {
+            if block.number > 0 && !self.block_hashes.contains_key(&(block.number - 1)) {
+                missing.push(MissingWitness::BlockHash(block.number - 1));
+            }
+        }
+
+        missing
+    }
+
+    // the hashes are only requested inside the BLOCKHASH window, a missing one is a hard error
+    pub fn block_hash(&self, number: u64) -> Result<B256, ProviderError> {
+        self.block_hashes
+            .get(&number)
+            .cloned()
+            .ok_or(ProviderError::HeaderNotFound(number.into()))
+    }
+
+    // the l2 contract from the input, or the one registered with the chain spec
+    pub fn l2_contract(&self) -> Option<Address> {
+        self.l2_contract
+            .or_else(|| get_chain_contracts(self.chain_id).and_then(|n| n.l2_contract))
+    }
+
+    pub fn get_acc<T: alloy_rlp::Decodable>(
+        &self,
+        addr: Address,
+    ) -> Result<Option<T>, ProviderError> {
+        let key = keccak256(addr);
+        let key = key.as_slice();
+        let result = self.mpt_nodes.get_rlp::<T>(key).map_err(|err| {
+            ProviderError::RPC(format!("get account[{:?}] fail: {}", addr, err.to_string()))
+        })?;
+        Ok(result)
+    }
+
+    pub fn get_slot<T: alloy_rlp::Decodable>(
+        &self,
+        key: Address,
+        root: B256,
+        slot: U256,
+    ) -> Result<Option<T>, ProviderError> {
+        let Some(storage_trie) = self.storage_mpt_nodes.get(&key) else {
+            return Ok(None);
+        };
+        let storage_hash = storage_trie.hash();
+        if storage_hash != root {
+            return Err(ProviderError::RPC(format!(
+                "slot[addr={:?},index={}] storage root mismatch: {:?}, want {:?}",
+                key, slot, storage_hash, root,
+            )));
+        }
+        let slot_key = keccak256(slot.to_be_bytes::<32>());
+        Ok(storage_trie.get_rlp(slot_key.as_slice()).map_err(|err| {
+            ProviderError::RPC(format!(
+                "fetch slot[addr={:?},index={}] fail: {}",
+                key,
+                slot,
+                err.to_string()
+            ))
+        })?)
+    }
+
+    // keep only the part of the witness that has been accessed during the execution
+    pub fn minimize(&self, accessed: &AccessedState) -> PobData {
+        let keys = accessed
+            .accounts
+            .iter()
+            .map(|addr| {
+                let deleted = accessed.deleted_accounts.contains(addr);
+                PruneKey::new(keccak256(addr).as_slice(), deleted)
+            })
+            .collect::<Vec<_>>();
+        let mpt_nodes = prune_trie(&self.mpt_nodes, &keys);
+
+        let mut code_hashes = accessed.codes.clone();
+        let mut storage_mpt_nodes = BTreeMap::new();
+        for (addr, storage_trie) in &self.storage_mpt_nodes {
+            if !accessed.accounts.contains(addr) {
+                continue;
+            }
+            let deleted_slots = accessed.deleted_slots.get(addr);
+            let keys = accessed
+                .slots
+                .get(addr)
+                .into_iter()
+                .flatten()
+                .map(|slot| {
+                    let deleted = deleted_slots.map_or(false, |n| n.contains(slot));
+                    PruneKey::new(keccak256(slot.to_be_bytes::<32>()).as_slice(), deleted)
+                })
+                .collect::<Vec<_>>();
+            storage_mpt_nodes.insert(*addr, prune_trie(storage_trie, &keys));
+
+            // the codes of the witnessed accounts are always required
+            if let Ok(Some(acc)) = self.get_acc::<StateAccount>(*addr) {
+                code_hashes.insert(acc.code_hash);
+            }
+        }
+        let codes = self
+            .codes
+            .iter()
+            .filter(|code| code_hashes.contains(&keccak256(code)))
+            .cloned()
+            .collect();
+
+        let parent_number = self.l2_parent_header.number;
+        let block_hashes = self
+            .block_hashes
+            .iter()
+            .filter(|(number, _)| {
+                **number == parent_number || accessed.block_hashes.contains(number)
+            })
+            .map(|(number, hash)| (*number, *hash))
+            .collect();
+
+        PobData {
+            chain_id: self.chain_id,
+            prev_state_root: self.prev_state_root,
+            block_hashes,
+            mpt_nodes,
+            storage_mpt_nodes,
+            codes,
+            l2_contract: self.l2_contract,
+            l1_header: self.l1_header.clone(),
+            l2_parent_header: self.l2_parent_header.clone(),
+            graffiti: self.graffiti,
+            l1_contract: self.l1_contract,
+            prover: self.prover,
+            block_meta: self.block_meta.clone(),
+            base_fee_config: self.base_fee_config.clone(),
+        }
+    }
+}
+
+// the ancestors must be a contiguous parent hash chain ending at the parent header
+pub fn verify_ancestor_headers(
+    parent: &Header,
+    ancestors: &[Header],
+) -> Result<BTreeMap<u64, B256>, PobError> {
+    let mut ancestors = ancestors.iter().collect::<Vec<_>>();
+    ancestors.sort_by_key(|header| std::cmp::Reverse(header.number));
+
+    let mut block_hashes = BTreeMap::new();
+    let mut child_number = parent.number;
+    let mut child_parent_hash = parent.parent_hash;
+    block_hashes.insert(parent.number, parent.hash_slow());
+    for header in ancestors {
+        let hash = header.hash_slow();
+        // the parent itself may be included
+        if header.number == parent.number && block_hashes.get(&header.number) == Some(&hash) {
+            continue;
+        }
+        // nothing comes before the genesis block
+        if header.number.checked_add(1) != Some(child_number) {
+            return Err(PobError::AncestorNotContiguous {
+                number: header.number,
+                want: child_number.checked_sub(1),
+            });
+        }
+        if hash != child_parent_hash {
+            return Err(PobError::AncestorHashMismatch {
+                number: header.number,
+                want: child_parent_hash,
+                got: hash,
+            });
+        }
+        block_hashes.insert(header.number, hash);
+        child_number = header.number;
+        child_parent_hash = header.parent_hash;
+    }
+    Ok(block_hashes)
+}
+
+// the l1 header must be the one committed by the metadata
+pub fn verify_l1_header(meta: &BlockMetaDataFork, l1_header: &Header) -> Result<(), PobError> {
+    let (fork, hash, height) = match meta {
+        BlockMetaDataFork::None => return Ok(()),
+        BlockMetaDataFork::Hekla(meta) => ("hekla", meta.l1Hash, meta.l1Height),
+        BlockMetaDataFork::Ontake(meta) => ("ontake", meta.anchorBlockHash, meta.anchorBlockId),
+    };
+    if l1_header.number != height {
+        return Err(PobError::L1HeightMismatch {
+            fork,
+            want: height,
+            got: l1_header.number,
+        });
+    }
+    let l1_hash = l1_header.hash_slow();
+    if l1_hash != hash {
+        return Err(PobError::L1HashMismatch {
+            fork,
+            want: hash,
+            got: l1_hash,
+        });
+    }
+    Ok(())
+}
+
+impl TryFrom<ProofInput> for Pob {
+    type Error = PobError;
+
+    fn try_from(value: ProofInput) -> Result<Self, Self::Error> {
+        let block_hashes = verify_ancestor_headers(&value.parent_header, &value.ancestor_headers)?;
+        if value.l2_block.parent_hash != value.parent_header.hash_slow() {
+            return Err(PobError::ParentHashMismatch {
+                want: value.l2_block.parent_hash,
+                got: value.parent_header.hash_slow(),
+            });
+        }
+        verify_l1_header(&value.taiko.metadata, &value.taiko.l1_header)?;
 
         let mut storage_mpt_nodes = BTreeMap::new();
         for (addr, (mpt, _)) in value.parent_storage {
@@ -78,10 +385,14 @@ impl From<ProofInput> for Pob {
             base_fee_config: get_base_fee_config(&value.taiko.metadata),
             block_meta: value.taiko.metadata,
         };
-        Self {
+        verify_anchor(&value.l2_block, &data)?;
+        if let Some(tx_list) = &value.taiko.tx_list {
+            verify_tx_list(&value.l2_block, &data, tx_list)?;
+        }
+        Ok(Self {
             block: value.l2_block,
             data,
-        }
+        })
     }
 }
 
@@ -93,6 +404,22 @@ pub enum BlockMetaDataFork {
 }
 
 pub fn guest_input_to_proof_input(input: GuestInput) -> Result<ProofInput, String> {
+    let metadata = select_block_meta(&input.taiko.block_proposed);
+    let blob_used = match &metadata {
+        BlockMetaDataFork::None => false,
+        BlockMetaDataFork::Hekla(meta) => meta.blobUsed,
+        BlockMetaDataFork::Ontake(meta) => meta.blobUsed,
+    };
+    // the tx data is the whole blob if the blob is used
+    let tx_list = match (blob_used, input.taiko.blob_commitment) {
+        (false, _) => TxList::Calldata(input.taiko.tx_data.into()),
+        (true, Some(commitment)) => TxList::Blob {
+            blob: input.taiko.tx_data.into(),
+            commitment: commitment.into(),
+        },
+        (true, None) => return Err("missing blob commitment".to_owned()),
+    };
+
     Ok(ProofInput {
         l2_block: input.block,
         parent_header: input.parent_header,
@@ -103,8 +430,9 @@ pub fn guest_input_to_proof_input(input: GuestInput) -> Result<ProofInput, Strin
         ancestor_headers: input.ancestor_headers,
         taiko: ProofTaikoInput {
             l1_header: input.taiko.l1_header,
-            metadata: select_block_meta(&input.taiko.block_proposed),
+            metadata,
             prover_data: input.taiko.prover_data,
+            tx_list: Some(tx_list),
         },
     })
 }
@@ -152,6 +480,8 @@ impl From<GuestInput> for Pob {
         for item in &value.ancestor_headers {
             block_hashes.insert(item.number, item.hash_slow());
         }
+        block_hashes.insert(value.parent_header.number, value.parent_header.hash_slow());
+
         let mut storage_mpt_nodes = BTreeMap::new();
         for (addr, (mpt, _)) in value.parent_storage {
             storage_mpt_nodes.insert(addr, mpt);
@@ -185,9 +515,9 @@ impl From<GuestInput> for Pob {
 impl BlockDataProvider for Pob {
     type ExtData = TaikoData;
 
-    fn ext_data(&self) -> Self::ExtData {
+    fn ext_data(&self, _: usize) -> Self::ExtData {
         TaikoData {
-            l2_contract: self.data.l2_contract.unwrap_or_default(),
+            l2_contract: self.data.l2_contract().unwrap_or_default(),
             l1_header: self.data.l1_header.clone(),
             parent_header: self.data.l2_parent_header.clone(),
             base_fee_config: unsafe { std::mem::transmute(self.data.base_fee_config.clone()) },
@@ -198,40 +528,36 @@ impl BlockDataProvider for Pob {
         self.data.chain_id
     }
 
-    fn block_hash(&self, number: u64) -> B256 {
-        self.data
-            .block_hashes
-            .get(&number)
-            .cloned()
-            .unwrap_or_default()
+    fn block_hash(&self, number: u64) -> Result<B256, ProviderError> {
+        self.data.block_hash(number)
     }
 
-    fn block(&self) -> &Block {
-        &self.block
+    fn blocks(&self) -> &[Block] {
+        std::slice::from_ref(&self.block)
     }
 
     fn contract_codes(&self) -> &[Bytes] {
         &self.data.codes
     }
 
-    fn state_trie(&self) -> &MptNode {
-        &self.data.mpt_nodes
+    fn state_trie(&self) -> DataProviderResult<&MptNode> {
+        Ok(&self.data.mpt_nodes)
     }
 
-    fn storage_state_trie(&self, addr: Address) -> &MptNode {
-        self.data
-            .storage_mpt_nodes
-            .get(&addr)
-            .expect("should have the storage trie")
+    fn storage_state_trie(&self, addr: Address) -> Option<&MptNode> {
+        self.data.storage_mpt_nodes.get(&addr)
+    }
+
+    fn check_witness(&self, blocks: &[BlockWithSenders]) -> DataProviderResult<()> {
+        let missing = self.data.missing_witness(blocks);
+        if !missing.is_empty() {
+            return Err(DataProviderError::IncompleteWitness(missing));
+        }
+        Ok(())
     }
 
     fn get_acc<T: alloy_rlp::Decodable>(&self, addr: Address) -> Result<Option<T>, ProviderError> {
-        let key = keccak256(addr);
-        let key = key.as_slice();
-        let result = self.data.mpt_nodes.get_rlp::<T>(key).map_err(|err| {
-            ProviderError::RPC(format!("get account[{:?}] fail: {}", addr, err.to_string()))
-        })?;
-        Ok(result)
+        self.data.get_acc(addr)
     }
 
     fn get_slot<T: alloy_rlp::Decodable>(
@@ -240,24 +566,55 @@ impl BlockDataProvider for Pob {
         root: B256,
         slot: U256,
     ) -> Result<Option<T>, ProviderError> {
-        let Some(storage_trie) = self.data.storage_mpt_nodes.get(&key) else {
-            return Ok(None);
-        };
-        let storage_hash = storage_trie.hash();
-        if storage_hash != root {
-            return Err(ProviderError::RPC(format!(
-                "slot[addr={:?},index={}] storage root mismatch: {:?}, want {:?}",
-                key, slot, storage_hash, root,
-            )));
+        self.data.get_slot(key, root, slot)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn chain(len: u64) -> Vec<Header> {
+        let mut headers: Vec<Header> = Vec::new();
+        for number in 0..len {
+            let parent_hash = headers.last().map(|h| h.hash_slow()).unwrap_or_default();
+            headers.push(Header {
+                number,
+                parent_hash,
+                ..Default::default()
+            });
         }
-        let slot_key = keccak256(slot.to_be_bytes::<32>());
-        Ok(storage_trie.get_rlp(slot_key.as_slice()).map_err(|err| {
-            ProviderError::RPC(format!(
-                "fetch slot[addr={:?},index={}] fail: {}",
-                key,
-                slot,
-                err.to_string()
-            ))
-        })?)
+        headers
+    }
+
+    #[test]
+    fn test_verify_ancestor_headers() {
+        let mut headers = chain(4);
+        let parent = headers.pop().unwrap();
+        let block_hashes = verify_ancestor_headers(&parent, &headers).unwrap();
+        assert_eq!(block_hashes.len(), 4);
+        assert_eq!(block_hashes[&0], headers[0].hash_slow());
+
+        let gap = [headers[0].clone()];
+        assert!(matches!(
+            verify_ancestor_headers(&parent, &gap),
+            Err(PobError::AncestorNotContiguous {
+                number: 0,
+                want: Some(2)
+            })
+        ));
+    }
+
+    #[test]
+    fn test_verify_ancestor_headers_of_genesis() {
+        let genesis = chain(1).pop().unwrap();
+        let ancestor = Header {
+            number: u64::MAX,
+            ..Default::default()
+        };
+        assert!(matches!(
+            verify_ancestor_headers(&genesis, &[ancestor]),
+            Err(PobError::AncestorNotContiguous { want: None, .. })
+        ));
     }
 }
@@ -1,11 +1,10 @@
 use alloy_sol_types::SolValue;
 use base::{Keypair, SecretKey};
-use executor::BlockDataProvider;
 use raiko_lib::primitives::keccak::keccak;
-use reth_primitives::{Address, Bytes, U256};
+use reth_primitives::{Address, Bytes, B256, U256};
 use serde::{Deserialize, Serialize};
 
-use crate::{meta_hash, Pob, ProveError};
+use crate::{meta_hash, PobData};
 
 alloy_sol_types::sol! {
     #[derive(Default, Debug, Deserialize, Serialize)]
@@ -34,15 +33,38 @@ fn _check() {
 }
 
 impl Poe {
-    pub fn signed_msg(&self, pob: &Pob, prover_registry: Address, new_instance: Address) -> Bytes {
+    pub fn signed_msg(
+        &self,
+        data: &PobData,
+        prover_registry: Address,
+        new_instance: Address,
+    ) -> Bytes {
+        self.signed_msg_by_parts(
+            data.chain_id,
+            prover_registry,
+            new_instance,
+            data.prover,
+            meta_hash(&data.block_meta),
+        )
+    }
+
+    // the message checked by ProverRegistryVerifier, without the whole PobData
+    pub fn signed_msg_by_parts(
+        &self,
+        chain_id: u64,
+        prover_registry: Address,
+        new_instance: Address,
+        prover: Address,
+        meta_hash: B256,
+    ) -> Bytes {
         let mut vec = (
             "VERIFY_PROOF",
-            pob.chain_id(),
+            chain_id,
             prover_registry,
             self.clone(),
             new_instance,
-            pob.data.prover,
-            meta_hash(&pob.data.block_meta),
+            prover,
+            meta_hash,
         )
             .abi_encode();
         vec = (&vec[32..]).into();
@@ -51,7 +73,7 @@ impl Poe {
 
     pub fn sign(
         self,
-        pob: &Pob,
+        data: &PobData,
         id: U256,
         prover_registry: Address,
         new_instance: Address,
@@ -60,7 +82,7 @@ impl Poe {
     ) -> SignedPoe {
         let sig = Keypair::sign_digest_ecdsa(
             sk,
-            keccak(self.signed_msg(pob, prover_registry, new_instance)),
+            keccak(self.signed_msg(data, prover_registry, new_instance)),
         );
 
         SignedPoe {
@@ -71,42 +93,4 @@ impl Poe {
             signature: sig.into(),
         }
     }
-
-    // the blockmeta we pick the last block inside the pob
-    // the parent_hash we pick the first block inside the poe
-    // the block_hash we pick the last block inside the poe
-    pub fn sign_multi(
-        poes: &[Poe],
-        pobs: &[Pob],
-        id: U256,
-        prover_registry: Address,
-        new_instance: Address,
-        sk: &SecretKey,
-        tee_type: U256,
-    ) -> Result<SignedPoe, ProveError> {
-        if poes.len() != pobs.len() || poes.len() == 0 {
-            return Err(ProveError::MissingPoe);
-        }
-        let last_pob = &pobs[pobs.len()-1];
-        let mut aggregated_poe = poes[poes.len()-1].clone();
-        for idx in 1..poes.len() {
-            let cur_poe = &poes[idx];
-            let prev_poe = &poes[idx];
-            if cur_poe.parent_hash != prev_poe.block_hash {
-                return Err(ProveError::BlockHashMismatch{ idx, cur: cur_poe.clone(), prev: prev_poe.clone()});
-            }
-        }
-        aggregated_poe.parent_hash = poes[0].parent_hash;
-        let sig = Keypair::sign_digest_ecdsa(
-            sk,
-            keccak(aggregated_poe.signed_msg(last_pob, prover_registry, new_instance)),
-        );
-        Ok(SignedPoe {
-            poe: aggregated_poe,
-            id,
-            new_instance,
-            teeType: tee_type,
-            signature: sig.into(),
-        })
-    }
 }
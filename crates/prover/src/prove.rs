@@ -1,7 +1,6 @@
 use alloy_primitives::Address;
-use alloy_sol_types::SolValue;
 use base::{stack_error, Keypair};
-use executor::{BlockExecutor, ExecutionError};
+use executor::{BlockExecutor, ExecutionError, ExecutionReport, Tracer};
 use jsonrpsee::{
     core::{async_trait, RpcResult},
     types::ErrorObject,
@@ -9,15 +8,17 @@ use jsonrpsee::{
 use raiko_core::{
     interfaces::ProofRequest as RpcProofRequest, provider::rpc::RpcBlockDataProvider, Raiko,
 };
-use raiko_lib::consts::SupportedChainSpecs;
+use raiko_lib::consts::{ChainSpec, SupportedChainSpecs};
 use raiko_lib::input::GuestInput;
-use reth_primitives::U256;
+use reth_primitives::{revm_primitives::HashMap, BlockWithSenders, B256, U256};
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 
 use crate::{
-    MultiProofRequest, Pob, Poe, ProofInput, ProofRequest, ProofResponse, ProverV1ApiServer,
-    SignedPoe,
+    guest_input_to_proof_input, guest_input_to_proof_inputs, BlockMetaDataFork, CrossCheckError,
+    GenMultiProofRequest, MultiPob, MultiProofInput, MultiProofRequest, Pob, PobError, Poe,
+    ProofCache, ProofCacheKey, ProofEncoding, ProofInput, ProofRequest, ProofResponse,
+    ProofTaikoInput, ProverV1ApiServer, SignedPoe, StateDiffResponse, TraceRequest, TraceResponse,
 };
 
 stack_error! {
@@ -25,11 +26,20 @@ stack_error! {
     stack_name: ProveErrorStack,
     error: {
         ProverNotRegistered,
-        MissingPoe,
-        BlockHashMismatch{ idx: usize, cur: Poe, prev: Poe },
+        EmptyProofInputs,
+        NonContiguousBlocks{ idx: usize, parent_hash: B256, prev_hash: B256 },
+        ChainIdMismatch{ idx: usize, want: u64, got: u64 },
+        MinimizedBlockMismatch{ want: B256, got: B256 },
+        ChainNotPinned{ chain_id: u64 },
+        L1ContractMismatch{ chain_id: u64, want: Address, got: Option<Address> },
+        L2ContractMismatch{ chain_id: u64, want: Address, got: Option<Address> },
+        CrossCheckMultiBlocks{ blocks: usize },
+        MissingTxList{ block_number: u64 },
     },
     wrap: {
         Execution(ExecutionError),
+        Pob(PobError),
+        CrossCheck(CrossCheckError),
         Json(serde_json::Error),
     },
     stack: {
@@ -39,92 +49,370 @@ stack_error! {
     }
 }
 
+// the chain the prover is allowed to sign for, along with its contracts
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PinnedChain {
+    pub chain_id: u64,
+    pub l1_contract: Address,
+    pub l2_contract: Address,
+}
+
+// the chain spec of the request must be one of the pinned chains, `None` means every
+// chain is allowed. nothing is signed if the list is empty.
+pub fn check_pinned_chain(
+    pinned: Option<&[PinnedChain]>,
+    chain_spec: &ChainSpec,
+) -> Result<(), ProveError> {
+    let Some(pinned) = pinned else {
+        return Ok(());
+    };
+    let chain_id = chain_spec.chain_id;
+    let chain = pinned
+        .iter()
+        .find(|n| n.chain_id == chain_id)
+        .ok_or(ProveError::ChainNotPinned { chain_id })?;
+    if chain_spec.l1_contract != Some(chain.l1_contract) {
+        return Err(ProveError::L1ContractMismatch {
+            chain_id,
+            want: chain.l1_contract,
+            got: chain_spec.l1_contract,
+        });
+    }
+    if chain_spec.l2_contract != Some(chain.l2_contract) {
+        return Err(ProveError::L2ContractMismatch {
+            chain_id,
+            want: chain.l2_contract,
+            got: chain_spec.l2_contract,
+        });
+    }
+    Ok(())
+}
+
+// the txs of a taiko block are only bound to its proposal by the tx list,
+// a block without it is signed only if it's allowed.
+fn check_tx_list(
+    block_number: u64,
+    taiko: &ProofTaikoInput,
+    allow_missing: bool,
+) -> Result<(), ProveError> {
+    if taiko.tx_list.is_some() || matches!(taiko.metadata, BlockMetaDataFork::None) {
+        return Ok(());
+    }
+    if !allow_missing {
+        return Err(ProveError::MissingTxList { block_number });
+    }
+    log::warn!("block {} is signed without its tx list", block_number);
+    Ok(())
+}
+
+// the block executed by us, waiting to be signed
+pub struct ExecutedProof {
+    pob: Arc<Pob>,
+    block: BlockWithSenders,
+    report: ExecutionReport,
+}
+
+impl ExecutedProof {
+    pub fn report(&self) -> &ExecutionReport {
+        &self.report
+    }
+}
+
+// with `cross_check`, the block is also executed by raiko and nothing is signed
+// unless both executors agree on the state root and the block hash.
 pub fn prove(
     input: ProofInput,
     prover_registry: Address,
     kp: &Keypair,
     tee_type: U256,
-) -> Result<SignedPoe, ProveError> {
-    let pob: Arc<Pob> = Arc::new(input.into());
-    let new_block = BlockExecutor::new(pob.clone()).execute()?;
+    cross_check: bool,
+    pinned: Option<&[PinnedChain]>,
+    allow_missing_tx_list: bool,
+) -> Result<(SignedPoe, ExecutionReport), ProveError> {
+    let executed = execute_proof_input(input, cross_check, pinned, allow_missing_tx_list)?;
+    sign_executed_proof(executed, prover_registry, kp, tee_type)
+}
+
+pub fn execute_proof_input(
+    input: ProofInput,
+    cross_check: bool,
+    pinned: Option<&[PinnedChain]>,
+    allow_missing_tx_list: bool,
+) -> Result<ExecutedProof, ProveError> {
+    check_pinned_chain(pinned, &input.chain_spec)?;
+    check_tx_list(input.l2_block.number, &input.taiko, allow_missing_tx_list)?;
+    let raiko_input = cross_check.then(|| input.clone());
+    let pob: Arc<Pob> = Arc::new(input.try_into()?);
+    let (block, report) = BlockExecutor::new(pob.clone()).execute_with_report()?;
+    log::info!("execution report: {:?}", report);
+    if let Some(input) = &raiko_input {
+        crate::cross_check(input, &block.header)?;
+    }
+    Ok(ExecutedProof { pob, block, report })
+}
+
+pub fn sign_executed_proof(
+    executed: ExecutedProof,
+    prover_registry: Address,
+    kp: &Keypair,
+    tee_type: U256,
+) -> Result<(SignedPoe, ExecutionReport), ProveError> {
+    let ExecutedProof { pob, block, report } = executed;
     let poe = Poe {
-        state_root: new_block.header.state_root,
+        state_root: block.header.state_root,
         parent_hash: pob.data.l2_parent_header.hash_slow(),
-        block_hash: new_block.hash_slow(),
+        block_hash: block.hash_slow(),
         graffiti: pob.data.graffiti,
     };
 
     let (id, addr, sk) = kp.info().ok_or(ProveError::ProverNotRegistered)?;
 
-    let poe = poe.sign(&pob, id, prover_registry, addr, &sk, tee_type);
+    let poe = poe.sign(&pob.data, id, prover_registry, addr, &sk, tee_type);
     log::info!("poe: {:?}", poe);
-    Ok(poe)
+    Ok((poe, report))
 }
 
-pub async fn prove_multi_blocks(
-    inputs: Vec<ProofInput>,
-    worker_num: usize,
+// drop the witness which is not accessed by the block, the minimized input
+// is executed again to make sure it still produces the same block.
+pub fn minimize_proof_input(input: &ProofInput) -> Result<ProofInput, ProveError> {
+    let pob: Pob = input.clone().try_into()?;
+    let (block, accessed) = BlockExecutor::new(Arc::new(pob.clone())).execute_with_access()?;
+    let data = pob.data.minimize(&accessed);
+
+    let mut parent_storage = HashMap::new();
+    for (addr, storage_trie) in data.storage_mpt_nodes {
+        let slots = match (input.parent_storage.get(&addr), accessed.slots.get(&addr)) {
+            (Some((_, slots)), Some(accessed_slots)) => slots
+                .iter()
+                .filter(|slot| accessed_slots.contains(slot))
+                .cloned()
+                .collect(),
+            _ => Vec::new(),
+        };
+        parent_storage.insert(addr, (storage_trie, slots));
+    }
+
+    // the ancestors are kept as a chain down to the oldest accessed one
+    let oldest = accessed
+        .block_hashes
+        .iter()
+        .next()
+        .cloned()
+        .unwrap_or(input.parent_header.number);
+    let ancestor_headers = input
+        .ancestor_headers
+        .iter()
+        .filter(|header| header.number >= oldest)
+        .cloned()
+        .collect();
+
+    let minimized = ProofInput {
+        l2_block: input.l2_block.clone(),
+        parent_header: input.parent_header.clone(),
+        chain_spec: input.chain_spec.clone(),
+        parent_state_trie: data.mpt_nodes,
+        parent_storage,
+        contracts: data.codes,
+        ancestor_headers,
+        taiko: input.taiko.clone(),
+    };
+
+    let pob: Arc<Pob> = Arc::new(minimized.clone().try_into()?);
+    let new_block = BlockExecutor::new(pob).execute()?;
+    if new_block.hash_slow() != block.hash_slow() {
+        return Err(ProveError::MinimizedBlockMismatch {
+            want: block.hash_slow(),
+            got: new_block.hash_slow(),
+        });
+    }
+    Ok(minimized)
+}
+
+// execute the block with the tracer, the traces are returned even if the execution fails
+pub fn trace(req: TraceRequest) -> TraceResponse {
+    let tracer = Tracer::new(req.tracer);
+    let error = match Pob::try_from(req.input) {
+        Ok(pob) => BlockExecutor::new(Arc::new(pob))
+            .execute_with_tracer(&tracer)
+            .err()
+            .map(|err| format!("{:?}", err)),
+        Err(err) => Some(format!("{:?}", err)),
+    };
+    TraceResponse {
+        traces: tracer.traces(),
+        error,
+    }
+}
+
+// execute the block without signing anything, for explaining what the block did
+pub fn state_diff(input: ProofInput) -> Result<StateDiffResponse, ProveError> {
+    let pob: Arc<Pob> = Arc::new(input.try_into()?);
+    let (new_block, diff) = BlockExecutor::new(pob).execute_with_diff()?;
+    Ok(StateDiffResponse {
+        block_hash: new_block.hash_slow(),
+        state_root: new_block.header.state_root,
+        diff,
+    })
+}
+
+// execute the consecutive blocks with one shared witness and sign an aggregated poe:
+// the parent_hash is the parent of the first block,
+// the block_hash, state_root and block meta are the ones of the last block.
+pub fn prove_multi_blocks(
+    input: MultiProofInput,
     prover_registry: Address,
     kp: &Keypair,
     tee_type: U256,
-) -> Result<SignedPoe, ProveError> {
-    let pobs = inputs
-        .iter()
-        .map(|n| n.clone().into())
-        .collect::<Vec<Pob>>();
-    let poes = base::parallel((), inputs, worker_num, |input, _| async move {
-        let pob: Arc<Pob> = Arc::new(input.into());
-        match BlockExecutor::new(pob.clone()).execute() {
-            Ok(new_block) => Ok(Poe {
-                state_root: new_block.header.state_root,
-                parent_hash: pob.data.l2_parent_header.hash_slow(),
-                block_hash: new_block.hash_slow(),
-                graffiti: pob.data.graffiti,
-            }),
-            Err(err) => return Err(err).map_err(ProveError::BlockNumber(&pob.block.number)),
+    cross_check: bool,
+    pinned: Option<&[PinnedChain]>,
+    allow_missing_tx_list: bool,
+) -> Result<(SignedPoe, ExecutionReport), ProveError> {
+    check_pinned_chain(pinned, &input.chain_spec)?;
+    for block in &input.blocks {
+        check_tx_list(block.l2_block.number, &block.taiko, allow_missing_tx_list)?;
+    }
+    // raiko executes a block against the witness of its parent state, which is only
+    // there for the first block, so a range of several blocks can't be cross checked.
+    let raiko_input = match cross_check {
+        true if input.blocks.len() > 1 => {
+            return Err(ProveError::CrossCheckMultiBlocks {
+                blocks: input.blocks.len(),
+            });
         }
-    })
-    .await?;
+        true => input.first_block_input(),
+        false => None,
+    };
+    let pob = Arc::new(MultiPob::from_input(input)?);
+    let (new_block, report) = BlockExecutor::new(pob.clone()).execute_with_report()?;
+    log::info!("execution report: {:?}", report);
+    if let Some(input) = &raiko_input {
+        crate::cross_check(input, &new_block.header)?;
+    }
+    let poe = Poe {
+        state_root: new_block.header.state_root,
+        parent_hash: pob.data.l2_parent_header.hash_slow(),
+        block_hash: new_block.hash_slow(),
+        graffiti: pob.data.graffiti,
+    };
 
     let (id, addr, sk) = kp.info().ok_or(ProveError::ProverNotRegistered)?;
-    let poe = Poe::sign_multi(&poes, &pobs, id, prover_registry, addr, &sk, tee_type)
-        .map_err(ProveError::SignPoe())?;
-    Ok(poe)
+    let poe = poe.sign(&pob.data, id, prover_registry, addr, &sk, tee_type);
+    log::info!("multi poe: {:?}", poe);
+    Ok((poe, report))
 }
 
+#[derive(Clone)]
 pub struct Prover {
     tee_type: U256,
     prover_registry: Address,
     kp: Keypair,
     worker_num: usize,
+    cross_check: bool,
+    pinned_chains: Vec<PinnedChain>,
+    allow_unpinned: bool,
+    allow_missing_tx_list: bool,
+    max_proofs_range: u64,
+    cache: Option<ProofCache>,
 }
 
 impl Prover {
+    pub const DEFAULT_MAX_PROOFS_RANGE: u64 = 32;
+
     pub fn new(kp: Keypair, prover_registry: Address, tee_type: U256, worker_num: usize) -> Self {
         Self {
             kp,
             prover_registry,
             tee_type,
             worker_num,
+            cross_check: false,
+            pinned_chains: Vec::new(),
+            allow_unpinned: false,
+            allow_missing_tx_list: false,
+            max_proofs_range: Self::DEFAULT_MAX_PROOFS_RANGE,
+            cache: None,
         }
     }
 
-    pub async fn get_proof(&self, req: RpcProofRequest) -> GuestInput {
-        let path = "./chain_spec_list.json".parse().unwrap();
-        let chain_specs = SupportedChainSpecs::merge_from_file(path).unwrap();
+    // serve the proofs already signed by the current instance from the cache
+    pub fn cache(mut self, cache: ProofCache) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    // only sign for these chains, nothing is signed if it's empty unless `allow_unpinned`
+    pub fn pinned_chains(mut self, pinned_chains: Vec<PinnedChain>) -> Self {
+        self.pinned_chains = pinned_chains;
+        self
+    }
+
+    // sign for every chain if no chain is pinned
+    pub fn allow_unpinned(mut self, allow_unpinned: bool) -> Self {
+        self.allow_unpinned = allow_unpinned;
+        self
+    }
+
+    // sign the taiko blocks proved without their tx list, their txs are unchecked
+    pub fn allow_missing_tx_list(mut self, allow_missing_tx_list: bool) -> Self {
+        self.allow_missing_tx_list = allow_missing_tx_list;
+        self
+    }
+
+    fn pinned(&self) -> Option<&[PinnedChain]> {
+        match self.allow_unpinned && self.pinned_chains.is_empty() {
+            true => None,
+            false => Some(&self.pinned_chains),
+        }
+    }
 
-        let taiko_chain_spec = chain_specs.get_chain_spec(&req.network).unwrap();
-        let l1_chain_spec = chain_specs.get_chain_spec(&req.l1_network).unwrap();
+    // the most blocks fetched by one `get_proofs`
+    pub fn max_proofs_range(mut self, max_proofs_range: u64) -> Self {
+        self.max_proofs_range = max_proofs_range;
+        self
+    }
+
+    // execute every block with raiko's executor as well before signing
+    pub fn cross_check(mut self, cross_check: bool) -> Self {
+        self.cross_check = cross_check;
+        self
+    }
+
+    pub async fn get_proof(&self, req: RpcProofRequest) -> RpcResult<GuestInput> {
+        let chain_specs = load_chain_specs()?;
+
+        let taiko_chain_spec = chain_specs.get_chain_spec(&req.network).ok_or_else(|| {
+            ErrorObject::owned(
+                14001,
+                format!("failed to get chain spec: {:?}", req.network),
+                None::<()>,
+            )
+        })?;
+        let l1_chain_spec = chain_specs.get_chain_spec(&req.l1_network).ok_or_else(|| {
+            ErrorObject::owned(
+                14001,
+                format!("failed to get chain spec: {:?}", req.l1_network),
+                None::<()>,
+            )
+        })?;
 
-        let provider = RpcBlockDataProvider::new(&taiko_chain_spec.rpc, req.block_number - 1)
-            .expect("Could not create RpcBlockDataProvider");
+        let parent_number = parent_block_number(req.block_number)?;
+        let provider =
+            RpcBlockDataProvider::new(&taiko_chain_spec.rpc, parent_number).map_err(|err| {
+                ErrorObject::owned(
+                    14001,
+                    format!("Could not create RpcBlockDataProvider: {:?}", err),
+                    None::<()>,
+                )
+            })?;
 
         let raiko = Raiko::new(l1_chain_spec, taiko_chain_spec, req.clone());
 
-        raiko
-            .generate_input(provider)
-            .await
-            .expect("input generation failed")
+        raiko.generate_input(provider).await.map_err(|err| {
+            ErrorObject::owned(
+                14001,
+                format!("Could not generate input: {:?}", err),
+                None::<()>,
+            )
+        })
     }
 
     pub async fn get_proofs(
@@ -133,10 +421,27 @@ impl Prover {
             request,
             start_block,
             end_block,
+            ..
         }: RpcMultiProofRequest,
     ) -> RpcResult<Vec<GuestInput>> {
-        let path = "./chain_spec_list.json".parse().unwrap();
-        let chain_specs = SupportedChainSpecs::merge_from_file(path).unwrap();
+        if end_block < start_block {
+            return Err(ErrorObject::owned(
+                14001,
+                format!("invalid block range: [{}, {}]", start_block, end_block),
+                None::<()>,
+            ));
+        }
+        if end_block - start_block >= self.max_proofs_range {
+            return Err(ErrorObject::owned(
+                14001,
+                format!(
+                    "block range too large: [{}, {}], max {} blocks",
+                    start_block, end_block, self.max_proofs_range
+                ),
+                None::<()>,
+            ));
+        }
+        let chain_specs = load_chain_specs()?;
 
         let taiko_chain_spec = chain_specs
             .get_chain_spec(&request.network)
@@ -157,7 +462,7 @@ impl Prover {
                 )
             })?;
 
-        let mut reqs = Vec::with_capacity((end_block - start_block) as usize);
+        let mut reqs = Vec::with_capacity((end_block - start_block + 1) as usize);
         for blk_num in start_block..=end_block {
             let mut tmp_req = request.clone();
             tmp_req.block_number = blk_num;
@@ -168,15 +473,15 @@ impl Prover {
             let l1_chain_spec = l1_chain_spec.clone();
             let taiko_chain_spec = taiko_chain_spec.clone();
             async move {
-                let provider =
-                    RpcBlockDataProvider::new(&taiko_chain_spec.rpc, req.block_number - 1)
-                        .map_err(|err| {
-                            ErrorObject::owned(
-                                14001,
-                                format!("Could not create RpcBlockDataProvider: {:?}", err),
-                                None::<()>,
-                            )
-                        })?;
+                let parent_number = parent_block_number(req.block_number)?;
+                let provider = RpcBlockDataProvider::new(&taiko_chain_spec.rpc, parent_number)
+                    .map_err(|err| {
+                        ErrorObject::owned(
+                            14001,
+                            format!("Could not create RpcBlockDataProvider: {:?}", err),
+                            None::<()>,
+                        )
+                    })?;
                 let raiko = Raiko::new(l1_chain_spec.clone(), taiko_chain_spec.clone(), req);
                 let proof = raiko.generate_input(provider).await.map_err(|err| {
                     ErrorObject::owned(
@@ -194,61 +499,208 @@ impl Prover {
     }
 
     pub fn prove(&self, req: ProofRequest) -> RpcResult<ProofResponse> {
-        let version = 1u64;
-        let signed_poe = prove(req.input, self.prover_registry, &self.kp, self.tee_type)
-            .map_err(|err| ErrorObject::owned(14001, format!("{:?}", err), None::<()>))?;
-
-        let id_be_bytes: [u8; 32] = signed_poe.id.to_be_bytes::<32>();
-        let id: [u8; 4] = id_be_bytes[28..].try_into().unwrap();
-        let new_instance: [u8; 20] = signed_poe.new_instance.into_array();
-        let signature: [u8; 65] = signed_poe.signature.to_vec().try_into().unwrap();
-
-        let data: [u8; 89] = [&id[..], &new_instance[..], &signature[..]]
-            .concat()
-            .try_into()
-            .unwrap();
+        let encoding = ProofEncoding::select(req.version, ProofEncoding::Packed)?;
+        self.prove_encoded(req, encoding)
+    }
 
-        Ok(ProofResponse {
-            version,
-            data: data.into(),
-        })
+    pub fn prove_encoded(
+        &self,
+        req: ProofRequest,
+        encoding: ProofEncoding,
+    ) -> RpcResult<ProofResponse> {
+        if let Some(response) = self.cached_proof(&req.input, encoding) {
+            return Ok(response);
+        }
+        let executed = self.execute(req)?;
+        self.sign(executed, encoding)
+    }
+
+    pub fn cached_proof(
+        &self,
+        input: &ProofInput,
+        encoding: ProofEncoding,
+    ) -> Option<ProofResponse> {
+        let (signed_poe, report) = self.lookup_cache(input)?;
+        Some(encoding.response(&signed_poe, report))
+    }
+
+    // a broken cache never fails the request, the block is proved again
+    fn lookup_cache(&self, input: &ProofInput) -> Option<(SignedPoe, ExecutionReport)> {
+        let cache = self.cache.as_ref()?;
+        // the chain may be unpinned since the proof is cached
+        check_pinned_chain(self.pinned(), &input.chain_spec).ok()?;
+        let (id, addr, _) = self.kp.info()?;
+        let key = ProofCacheKey::from_input(input);
+        match cache.get(&key, id, addr) {
+            Ok(Some(n)) => {
+                log::info!("cached proof hit: {:?}", key);
+                Some(n)
+            }
+            Ok(None) => None,
+            Err(err) => {
+                log::error!("read proof cache fail: {:?}", err);
+                None
+            }
+        }
     }
 
-    pub async fn prove_multi(&self, req: MultiProofRequest) -> RpcResult<ProofResponse> {
-        let version = 1u64;
-        let signed_poe =
-            prove_multi_blocks(req.input, 4, self.prover_registry, &self.kp, self.tee_type)
-                .await
+    fn sign_and_cache(&self, executed: ExecutedProof) -> RpcResult<(SignedPoe, ExecutionReport)> {
+        let key = ProofCacheKey::from_pob_data(&executed.pob.data, executed.block.hash_slow());
+        let (signed_poe, report) =
+            sign_executed_proof(executed, self.prover_registry, &self.kp, self.tee_type)
                 .map_err(|err| ErrorObject::owned(14001, format!("{:?}", err), None::<()>))?;
+        if let Some(cache) = &self.cache {
+            if let Err(err) = cache.put(&key, &signed_poe, &report) {
+                log::error!("write proof cache fail: {:?}", err);
+            }
+        }
+        Ok((signed_poe, report))
+    }
+
+    // the execution half of `prove`, `sign` is expected to follow
+    pub fn execute(&self, req: ProofRequest) -> RpcResult<ExecutedProof> {
+        execute_proof_input(
+            req.input,
+            self.cross_check,
+            self.pinned(),
+            self.allow_missing_tx_list,
+        )
+        .map_err(|err| ErrorObject::owned(14001, format!("{:?}", err), None::<()>))
+    }
 
-        let id_be_bytes: [u8; 32] = signed_poe.id.to_be_bytes::<32>();
-        let id: [u8; 4] = id_be_bytes[28..].try_into().unwrap();
-        let new_instance: [u8; 20] = signed_poe.new_instance.into_array();
-        let signature: [u8; 65] = signed_poe.signature.to_vec().try_into().unwrap();
+    pub fn sign(
+        &self,
+        executed: ExecutedProof,
+        encoding: ProofEncoding,
+    ) -> RpcResult<ProofResponse> {
+        let (signed_poe, report) = self.sign_and_cache(executed)?;
+        Ok(encoding.response(&signed_poe, report))
+    }
 
-        let data: [u8; 89] = [&id[..], &new_instance[..], &signature[..]]
-            .concat()
-            .try_into()
-            .unwrap();
+    // the inputs of the blocks to prove together, the range is fetched by raiko
+    pub async fn multi_proof_inputs(
+        &self,
+        req: GenMultiProofRequest,
+    ) -> RpcResult<MultiProofInput> {
+        match req {
+            GenMultiProofRequest::Inputs(req) => Ok(req.input),
+            GenMultiProofRequest::Range(req) => {
+                let guest_inputs = self.get_proofs(req).await?;
+                let inputs = guest_input_to_proof_inputs(guest_inputs)
+                    .map_err(|err| ErrorObject::owned(14001, err, None::<()>))?;
+                MultiProofInput::from_inputs(inputs)
+                    .map_err(|err| ErrorObject::owned(14001, format!("{:?}", err), None::<()>))
+            }
+        }
+    }
 
-        Ok(ProofResponse {
-            version,
-            data: data.into(),
-        })
+    pub fn trace(&self, req: TraceRequest) -> TraceResponse {
+        trace(req)
+    }
+
+    pub fn state_diff(&self, req: ProofRequest) -> RpcResult<StateDiffResponse> {
+        state_diff(req.input)
+            .map_err(|err| ErrorObject::owned(14001, format!("{:?}", err), None::<()>))
+    }
+
+    pub fn prove_multi(&self, req: MultiProofRequest) -> RpcResult<ProofResponse> {
+        let encoding = ProofEncoding::select(req.version, ProofEncoding::Packed)?;
+        self.prove_multi_encoded(req.input, encoding)
+    }
+
+    pub fn prove_multi_encoded(
+        &self,
+        input: MultiProofInput,
+        encoding: ProofEncoding,
+    ) -> RpcResult<ProofResponse> {
+        let (signed_poe, report) = prove_multi_blocks(
+            input,
+            self.prover_registry,
+            &self.kp,
+            self.tee_type,
+            self.cross_check,
+            self.pinned(),
+            self.allow_missing_tx_list,
+        )
+        .map_err(|err| ErrorObject::owned(14001, format!("{:?}", err), None::<()>))?;
+        Ok(encoding.response(&signed_poe, report))
     }
 }
 
+fn load_chain_specs() -> RpcResult<SupportedChainSpecs> {
+    let path = "./chain_spec_list.json";
+    SupportedChainSpecs::merge_from_file(path.into()).map_err(|err| {
+        ErrorObject::owned(
+            14001,
+            format!("failed to load chain specs from {}: {:?}", path, err),
+            None::<()>,
+        )
+    })
+}
+
+// the genesis block has nothing to prove
+fn parent_block_number(block_number: u64) -> RpcResult<u64> {
+    block_number.checked_sub(1).ok_or_else(|| {
+        ErrorObject::owned(
+            14001,
+            format!("block {} has no parent", block_number),
+            None::<()>,
+        )
+    })
+}
+
+// the execution and the signing block the thread, they run on the blocking pool so the
+// server keeps serving the other requests. a panic fails the request only.
+pub async fn spawn_prover<T, F>(prover: &Prover, f: F) -> RpcResult<T>
+where
+    T: Send + 'static,
+    F: FnOnce(Prover) -> RpcResult<T> + Send + 'static,
+{
+    let prover = prover.clone();
+    tokio::task::spawn_blocking(move || f(prover))
+        .await
+        .map_err(|err| {
+            ErrorObject::owned(14001, format!("prover task fail: {:?}", err), None::<()>)
+        })?
+}
+
 #[async_trait]
 impl ProverV1ApiServer for Prover {
     async fn gen_proof(&self, req: ProofRequest) -> RpcResult<ProofResponse> {
-        let version = 1u64;
+        let encoding = ProofEncoding::select(req.version, ProofEncoding::Abi)?;
+        spawn_prover(self, move |prover| prover.prove_encoded(req, encoding)).await
+    }
 
-        let response = prove(req.input, self.prover_registry, &self.kp, self.tee_type)
-            .map_err(|err| ErrorObject::owned(14001, format!("{:?}", err), None::<()>))?;
-        Ok(ProofResponse {
-            version,
-            data: response.abi_encode().into(),
+    async fn gen_multi_proof(&self, req: GenMultiProofRequest) -> RpcResult<ProofResponse> {
+        let encoding = ProofEncoding::select(req.version(), ProofEncoding::Abi)?;
+        let input = self.multi_proof_inputs(req).await?;
+        spawn_prover(self, move |prover| {
+            prover.prove_multi_encoded(input, encoding)
         })
+        .await
+    }
+
+    async fn get_block_proof(
+        &self,
+        req: RpcProofRequest,
+        version: Option<u64>,
+    ) -> RpcResult<ProofResponse> {
+        let encoding = ProofEncoding::select(version, ProofEncoding::Packed)?;
+        let guest_input = self.get_proof(req).await?;
+        spawn_prover(self, move |prover| {
+            let input = guest_input_to_proof_input(guest_input)
+                .map_err(|err| ErrorObject::owned(14001, err, None::<()>))?;
+            prover.prove_encoded(ProofRequest { input, version }, encoding)
+        })
+        .await
+    }
+
+    async fn trace_block(&self, req: TraceRequest) -> RpcResult<TraceResponse> {
+        spawn_prover(self, move |prover| Ok(prover.trace(req))).await
+    }
+
+    async fn state_diff(&self, req: ProofRequest) -> RpcResult<StateDiffResponse> {
+        spawn_prover(self, move |prover| Prover::state_diff(&prover, req)).await
     }
 }
 
@@ -257,4 +709,132 @@ pub struct RpcMultiProofRequest {
     pub request: RpcProofRequest,
     pub start_block: u64,
     pub end_block: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<u64>,
+}
+
+#[cfg(test)]
+mod test {
+    use executor::{TraceResult, TracerKind};
+
+    use super::*;
+    use crate::{testutil::load_request, TxList};
+
+    fn load_chain_spec() -> ChainSpec {
+        load_request().input.chain_spec
+    }
+
+    #[test]
+    fn test_check_pinned_chain() {
+        let chain_spec = load_chain_spec();
+        let pinned = PinnedChain {
+            chain_id: chain_spec.chain_id,
+            l1_contract: chain_spec.l1_contract.unwrap(),
+            l2_contract: chain_spec.l2_contract.unwrap(),
+        };
+        check_pinned_chain(Some(&[pinned.clone()]), &chain_spec).unwrap();
+        check_pinned_chain(None, &chain_spec).unwrap();
+
+        // fail closed if nothing is pinned
+        assert!(matches!(
+            check_pinned_chain(Some(&[]), &chain_spec),
+            Err(ProveError::ChainNotPinned { .. })
+        ));
+
+        let other = PinnedChain {
+            l2_contract: Address::with_last_byte(1),
+            ..pinned
+        };
+        assert!(matches!(
+            check_pinned_chain(Some(&[other]), &chain_spec),
+            Err(ProveError::L2ContractMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn test_check_tx_list() {
+        let input = load_request().input;
+        let number = input.l2_block.number;
+        assert!(matches!(
+            check_tx_list(number, &input.taiko, false),
+            Err(ProveError::MissingTxList { block_number }) if block_number == number
+        ));
+        check_tx_list(number, &input.taiko, true).unwrap();
+
+        let mut taiko = input.taiko.clone();
+        taiko.tx_list = Some(TxList::Calldata(Default::default()));
+        check_tx_list(number, &taiko, false).unwrap();
+        taiko.tx_list = None;
+        taiko.metadata = BlockMetaDataFork::None;
+        check_tx_list(number, &taiko, false).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_max_proofs_range() {
+        let prover = Prover::new(Keypair::new(), Address::ZERO, U256::ZERO, 1).max_proofs_range(2);
+        let req = |start_block, end_block| RpcMultiProofRequest {
+            request: Default::default(),
+            start_block,
+            end_block,
+            version: None,
+        };
+        for (start, end) in [(10, 12), (0, u64::MAX), (10, 9)] {
+            let err = prover.get_proofs(req(start, end)).await.unwrap_err();
+            assert_eq!(err.code(), 14001);
+        }
+    }
+
+    #[test]
+    fn test_cross_check_multi_blocks() {
+        let mut input = MultiProofInput::from_inputs(vec![load_request().input]).unwrap();
+        input.blocks.push(input.blocks[0].clone());
+
+        let err = prove_multi_blocks(
+            input,
+            Address::ZERO,
+            &Keypair::new(),
+            U256::ZERO,
+            true,
+            None,
+            true,
+        )
+        .unwrap_err();
+        assert!(matches!(
+            err,
+            ProveError::CrossCheckMultiBlocks { blocks: 2 }
+        ));
+    }
+
+    #[test]
+    fn test_trace_matches_execute() {
+        let input = load_request().input;
+
+        let pob = Arc::new(Pob::try_from(input.clone()).unwrap());
+        let block = BlockExecutor::new(pob.clone()).execute().unwrap();
+        let tracer = Tracer::new(TracerKind::CallTracer);
+        let traced = BlockExecutor::new(pob)
+            .execute_with_tracer(&tracer)
+            .unwrap();
+        assert_eq!(traced.header.state_root, block.header.state_root);
+        assert_eq!(traced.header.hash_slow(), block.header.hash_slow());
+        assert_eq!(block.header.hash_slow(), input.l2_block.hash_slow());
+
+        let traces = tracer.traces();
+        assert_eq!(traces.len(), 1);
+        assert_eq!(traces[0].hash, block.header.hash_slow());
+        assert_eq!(traces[0].txs.len(), input.l2_block.body.len());
+        for (trace, tx) in traces[0].txs.iter().zip(&input.l2_block.body) {
+            assert_eq!(trace.tx_hash, Some(tx.hash()));
+            assert!(matches!(trace.result, TraceResult::Call(_)));
+        }
+
+        // the anchor tx calls the l2 contract
+        let TraceResult::Call(anchor) = &traces[0].txs[0].result else {
+            unreachable!()
+        };
+        assert_eq!(anchor.typ, "CALL");
+        assert_eq!(anchor.to, input.chain_spec.l2_contract);
+        assert!(anchor.gas_used > 0);
+        assert!(anchor.error.is_none());
+    }
 }
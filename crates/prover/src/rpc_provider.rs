@@ -0,0 +1,908 @@
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, Mutex, OnceLock},
+};
+
+use alloy_rpc_types::EIP1186AccountProofResponse;
+use base::stack_error;
+use executor::{
+    BlockDataProvider, BlockExecutor, DataProviderError, DataProviderResult, ExecutionError,
+};
+use jsonrpsee::{
+    core::{client::ClientT, params::ArrayParams},
+    http_client::{HttpClient, HttpClientBuilder},
+};
+use raiko_lib::{
+    consts::ChainSpec as RaikoChainSpec,
+    primitives::mpt::{proofs_to_tries, MptNode, StateAccount, StorageEntry},
+};
+use reth_evm::execute::ProviderError;
+use reth_evm_ethereum::taiko::TaikoData;
+use reth_primitives::{
+    constants::EMPTY_ROOT_HASH,
+    revm_primitives::{HashMap, KECCAK_EMPTY},
+    Address, Block, BlockWithSenders, Bytes, Header, B256, U256,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{get_base_fee_config, ProofInput, ProofTaikoInput};
+
+stack_error! {
+    name: RpcProviderError,
+    stack_name: RpcProviderErrorStack,
+    error: {
+        Rpc{ method: String, err: String },
+        RecordNotFound{ method: String, params: String },
+        BlockNotFound(u64),
+        GenesisBlock,
+        ConvertBlock(String),
+        BuildTrie(String),
+    },
+    wrap: {
+        Json(serde_json::Error),
+        Execution(ExecutionError),
+        Io(std::io::Error),
+    },
+    stack: {
+        Call(method: String),
+        FetchBlock(number: u64),
+    }
+}
+
+// the json-rpc transport used by the RpcProvider
+pub trait RpcTransport: Send + Sync {
+    fn call(&self, method: &str, params: Vec<Value>) -> Result<Value, RpcProviderError>;
+}
+
+// the http transport, the requests are sent by a dedicated runtime in a scoped thread,
+// so it can be used inside or outside of an async context.
+pub struct HttpTransport {
+    client: HttpClient,
+    runtime: tokio::runtime::Runtime,
+}
+
+impl HttpTransport {
+    pub fn new(url: &str) -> Result<Self, RpcProviderError> {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()?;
+        let client = {
+            let _guard = runtime.enter();
+            HttpClientBuilder::default()
+                .max_request_size(100 << 20)
+                .max_response_size(100 << 20)
+                .build(url)
+                .map_err(|err| RpcProviderError::Rpc {
+                    method: "dial".into(),
+                    err: err.to_string(),
+                })?
+        };
+        Ok(Self { client, runtime })
+    }
+}
+
+impl RpcTransport for HttpTransport {
+    fn call(&self, method: &str, params: Vec<Value>) -> Result<Value, RpcProviderError> {
+        let mut rpc_params = ArrayParams::new();
+        for param in params {
+            rpc_params.insert(param)?;
+        }
+        let result = std::thread::scope(|s| {
+            s.spawn(|| {
+                self.runtime
+                    .block_on(self.client.request::<Value, _>(method, rpc_params))
+            })
+            .join()
+            .unwrap()
+        });
+        result.map_err(|err| RpcProviderError::Rpc {
+            method: method.to_owned(),
+            err: err.to_string(),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RpcRecord {
+    pub method: String,
+    pub params: Vec<Value>,
+    pub result: Value,
+}
+
+fn record_key(method: &str, params: &[Value]) -> String {
+    format!("{}{}", method, Value::Array(params.to_vec()))
+}
+
+// forwards the calls to the inner transport and records the responses
+pub struct RecordingTransport<T: RpcTransport> {
+    inner: T,
+    records: Mutex<Vec<RpcRecord>>,
+}
+
+impl<T: RpcTransport> RecordingTransport<T> {
+    pub fn new(inner: T) -> Self {
+        Self {
+            inner,
+            records: Mutex::new(Vec::new()),
+        }
+    }
+
+    pub fn records(&self) -> Vec<RpcRecord> {
+        self.records.lock().unwrap().clone()
+    }
+}
+
+impl<T: RpcTransport> RpcTransport for RecordingTransport<T> {
+    fn call(&self, method: &str, params: Vec<Value>) -> Result<Value, RpcProviderError> {
+        let result = self.inner.call(method, params.clone())?;
+        self.records.lock().unwrap().push(RpcRecord {
+            method: method.to_owned(),
+            params,
+            result: result.clone(),
+        });
+        Ok(result)
+    }
+}
+
+// the stand-in of a node, it replays the recorded responses
+pub struct RecordedTransport {
+    responses: BTreeMap<String, Value>,
+}
+
+impl RecordedTransport {
+    pub fn new(records: Vec<RpcRecord>) -> Self {
+        let responses = records
+            .into_iter()
+            .map(|record| (record_key(&record.method, &record.params), record.result))
+            .collect();
+        Self { responses }
+    }
+
+    pub fn from_file(path: &str) -> Result<Self, RpcProviderError> {
+        let data = std::fs::read(path)?;
+        Ok(Self::new(serde_json::from_slice(&data)?))
+    }
+}
+
+impl RpcTransport for RecordedTransport {
+    fn call(&self, method: &str, params: Vec<Value>) -> Result<Value, RpcProviderError> {
+        let key = record_key(method, &params);
+        match self.responses.get(&key) {
+            Some(result) => Ok(result.clone()),
+            None => Err(RpcProviderError::RecordNotFound {
+                method: method.to_owned(),
+                params: Value::Array(params).to_string(),
+            }),
+        }
+    }
+}
+
+#[derive(Default)]
+struct FetchedState {
+    // the proofs against the parent state, the storage proofs of an account are merged
+    proofs: BTreeMap<Address, EIP1186AccountProofResponse>,
+    codes: BTreeMap<B256, Bytes>,
+    headers: BTreeMap<u64, Header>,
+}
+
+struct Witness {
+    state_trie: MptNode,
+    storage: HashMap<Address, StorageEntry>,
+}
+
+// the BlockDataProvider fetching the witness of the block on demand from an l2 node,
+// the fetched witness can be assembled into a ProofInput after the execution.
+pub struct RpcProvider {
+    transport: Arc<dyn RpcTransport>,
+    chain_spec: RaikoChainSpec,
+    taiko: ProofTaikoInput,
+    block: Block,
+    parent_header: Header,
+
+    fetched: Mutex<FetchedState>,
+    // built from the fetched proofs once the execution starts to apply the changes
+    witness: OnceLock<Result<Witness, String>>,
+}
+
+impl RpcProvider {
+    pub fn new(
+        transport: Arc<dyn RpcTransport>,
+        chain_spec: RaikoChainSpec,
+        block_number: u64,
+        taiko: ProofTaikoInput,
+    ) -> Result<Self, RpcProviderError> {
+        let block = fetch_block(transport.as_ref(), block_number, true)?;
+        let block: Block = block
+            .try_into()
+            .map_err(|err| RpcProviderError::ConvertBlock(format!("{:?}", err)))
+            .map_err(RpcProviderError::FetchBlock(&block_number))?;
+        // the genesis block has nothing to prove
+        let parent_number = block_number
+            .checked_sub(1)
+            .ok_or(RpcProviderError::GenesisBlock)?;
+        let parent = fetch_block(transport.as_ref(), parent_number, false)?;
+        let parent_header: Header = parent
+            .header
+            .try_into()
+            .map_err(|err| RpcProviderError::ConvertBlock(format!("{:?}", err)))
+            .map_err(RpcProviderError::FetchBlock(&parent_number))?;
+
+        let mut fetched = FetchedState::default();
+        fetched
+            .headers
+            .insert(parent_header.number, parent_header.clone());
+        Ok(Self {
+            transport,
+            chain_spec,
+            taiko,
+            block,
+            parent_header,
+            fetched: Mutex::new(fetched),
+            witness: OnceLock::new(),
+        })
+    }
+
+    fn call<T: serde::de::DeserializeOwned>(
+        &self,
+        method: &str,
+        params: Vec<Value>,
+    ) -> Result<T, RpcProviderError> {
+        let result = self
+            .transport
+            .call(method, params)
+            .map_err(RpcProviderError::Call(&method.to_owned()))?;
+        Ok(serde_json::from_value(result).map_err(RpcProviderError::Call(&method.to_owned()))?)
+    }
+
+    fn parent_tag(&self) -> Value {
+        format!("{:#x}", self.parent_header.number).into()
+    }
+
+    fn get_proof(
+        &self,
+        addr: Address,
+        slots: &[U256],
+        tag: Value,
+    ) -> Result<EIP1186AccountProofResponse, RpcProviderError> {
+        let slots = slots
+            .iter()
+            .map(|slot| B256::from(slot.to_be_bytes::<32>()))
+            .collect::<Vec<_>>();
+        self.call(
+            "eth_getProof",
+            vec![
+                serde_json::to_value(addr)?,
+                serde_json::to_value(slots)?,
+                tag,
+            ],
+        )
+    }
+
+    // fetch the account proof against the parent state, and the code if the account has any
+    fn fetch_account(
+        &self,
+        addr: Address,
+    ) -> Result<EIP1186AccountProofResponse, RpcProviderError> {
+        if let Some(proof) = self.fetched.lock().unwrap().proofs.get(&addr) {
+            return Ok(proof.clone());
+        }
+        let proof = self.get_proof(addr, &[], self.parent_tag())?;
+        let mut code = None;
+        if proof.code_hash != KECCAK_EMPTY && proof.code_hash != B256::ZERO {
+            let params = vec![serde_json::to_value(addr)?, self.parent_tag()];
+            code = Some(self.call::<Bytes>("eth_getCode", params)?);
+        }
+
+        let mut fetched = self.fetched.lock().unwrap();
+        if let Some(code) = code {
+            fetched.codes.insert(proof.code_hash, code);
+        }
+        Ok(fetched.proofs.entry(addr).or_insert(proof).clone())
+    }
+
+    fn fetch_slot(&self, addr: Address, slot: U256) -> Result<U256, RpcProviderError> {
+        let key = B256::from(slot.to_be_bytes::<32>());
+        self.fetch_account(addr)?;
+        if let Some(proof) = self.fetched.lock().unwrap().proofs.get(&addr) {
+            if let Some(storage) = proof.storage_proof.iter().find(|n| n.key.0 == key) {
+                return Ok(storage.value);
+            }
+        }
+
+        let mut proof = self.get_proof(addr, &[slot], self.parent_tag())?;
+        let Some(storage) = proof.storage_proof.pop() else {
+            return Ok(U256::ZERO);
+        };
+        let value = storage.value;
+        let mut fetched = self.fetched.lock().unwrap();
+        if let Some(account) = fetched.proofs.get_mut(&addr) {
+            account.storage_proof.push(storage);
+        }
+        Ok(value)
+    }
+
+    fn fetch_header(&self, number: u64) -> Result<Header, RpcProviderError> {
+        if let Some(header) = self.fetched.lock().unwrap().headers.get(&number) {
+            return Ok(header.clone());
+        }
+        let block = fetch_block(self.transport.as_ref(), number, false)?;
+        let header: Header = block
+            .header
+            .try_into()
+            .map_err(|err| RpcProviderError::ConvertBlock(format!("{:?}", err)))
+            .map_err(RpcProviderError::FetchBlock(&number))?;
+        let mut fetched = self.fetched.lock().unwrap();
+        Ok(fetched.headers.entry(number).or_insert(header).clone())
+    }
+
+    // the parent proofs only cover the nodes on the paths of the accessed keys,
+    // the proofs against the post state reveal the nodes required by deletions.
+    fn build_witness(&self) -> Result<Witness, RpcProviderError> {
+        let parent_proofs = self.fetched.lock().unwrap().proofs.clone();
+        let block_tag: Value = format!("{:#x}", self.block.number).into();
+        let mut proofs = HashMap::new();
+        for (addr, parent_proof) in &parent_proofs {
+            let slots = parent_proof
+                .storage_proof
+                .iter()
+                .map(|n| U256::from_be_bytes(n.key.0 .0))
+                .collect::<Vec<_>>();
+            let proof = self.get_proof(*addr, &slots, block_tag.clone())?;
+            proofs.insert(*addr, proof);
+        }
+        let parent_proofs = parent_proofs.into_iter().collect::<HashMap<_, _>>();
+
+        let (state_trie, storage) =
+            proofs_to_tries(self.parent_header.state_root, parent_proofs, proofs)
+                .map_err(|err| RpcProviderError::BuildTrie(err.to_string()))?;
+        Ok(Witness {
+            state_trie,
+            storage,
+        })
+    }
+
+    fn witness(&self) -> Result<&Witness, RpcProviderError> {
+        let witness = self.witness.get_or_init(|| {
+            self.build_witness().map_err(|err| {
+                log::error!("build witness from proofs fail: {:?}", err);
+                format!("{:?}", err)
+            })
+        });
+        witness
+            .as_ref()
+            .map_err(|err| RpcProviderError::BuildTrie(err.clone()))
+    }
+
+    // assemble the ProofInput from the witness fetched during the execution
+    pub fn build_proof_input(&self) -> Result<ProofInput, RpcProviderError> {
+        let witness = self.witness()?;
+        let fetched = self.fetched.lock().unwrap();
+
+        // the ancestors are kept as a chain down to the oldest accessed one
+        let oldest = fetched
+            .headers
+            .keys()
+            .next()
+            .cloned()
+            .unwrap_or(self.parent_header.number);
+        drop(fetched);
+        let mut ancestor_headers = Vec::new();
+        for number in (oldest..self.parent_header.number).rev() {
+            ancestor_headers.push(self.fetch_header(number)?);
+        }
+
+        let fetched = self.fetched.lock().unwrap();
+        Ok(ProofInput {
+            l2_block: self.block.clone(),
+            parent_header: self.parent_header.clone(),
+            chain_spec: self.chain_spec.clone(),
+            parent_state_trie: witness.state_trie.clone(),
+            parent_storage: witness.storage.clone(),
+            contracts: fetched.codes.values().cloned().collect(),
+            ancestor_headers,
+            taiko: self.taiko.clone(),
+        })
+    }
+}
+
+fn fetch_block(
+    transport: &dyn RpcTransport,
+    number: u64,
+    full: bool,
+) -> Result<alloy_rpc_types::Block, RpcProviderError> {
+    let params = vec![format!("{:#x}", number).into(), full.into()];
+    let result = transport
+        .call("eth_getBlockByNumber", params)
+        .map_err(RpcProviderError::FetchBlock(&number))?;
+    if result.is_null() {
+        return Err(RpcProviderError::BlockNotFound(number));
+    }
+    Ok(serde_json::from_value(result).map_err(RpcProviderError::FetchBlock(&number))?)
+}
+
+// execute the block against the node and collect the witness it requires
+pub fn generate_proof_input(
+    transport: Arc<dyn RpcTransport>,
+    chain_spec: RaikoChainSpec,
+    block_number: u64,
+    taiko: ProofTaikoInput,
+) -> Result<ProofInput, RpcProviderError> {
+    let provider = Arc::new(RpcProvider::new(
+        transport,
+        chain_spec,
+        block_number,
+        taiko,
+    )?);
+    BlockExecutor::new(provider.clone()).execute()?;
+    provider.build_proof_input()
+}
+
+fn provider_error(err: RpcProviderError) -> ProviderError {
+    ProviderError::RPC(format!("{:?}", err))
+}
+
+impl BlockDataProvider for RpcProvider {
+    type ExtData = TaikoData;
+
+    fn ext_data(&self, _: usize) -> Self::ExtData {
+        let base_fee_config = get_base_fee_config(&self.taiko.metadata);
+        TaikoData {
+            l2_contract: self.chain_spec.l2_contract.unwrap_or_default(),
+            l1_header: self.taiko.l1_header.clone(),
+            parent_header: self.parent_header.clone(),
+            base_fee_config: unsafe { std::mem::transmute(base_fee_config) },
+        }
+    }
+
+    fn chain_id(&self) -> u64 {
+        self.chain_spec.chain_id
+    }
+
+    fn blocks(&self) -> &[Block] {
+        std::slice::from_ref(&self.block)
+    }
+
+    // the codes are served by `code_by_hash`
+    fn contract_codes(&self) -> &[Bytes] {
+        &[]
+    }
+
+    fn code_by_hash(&self, code_hash: B256) -> Option<Bytes> {
+        self.fetched.lock().unwrap().codes.get(&code_hash).cloned()
+    }
+
+    fn block_hash(&self, number: u64) -> Result<B256, ProviderError> {
+        let header = self.fetch_header(number).map_err(provider_error)?;
+        Ok(header.hash_slow())
+    }
+
+    // reading the tries before the execution is done would build the witness
+    // from the proofs fetched so far
+    fn has_witness(&self) -> bool {
+        false
+    }
+
+    fn state_trie(&self) -> DataProviderResult<&MptNode> {
+        let witness = self
+            .witness()
+            .map_err(|err| DataProviderError::BuildWitness(format!("{:?}", err)))?;
+        Ok(&witness.state_trie)
+    }
+
+    fn storage_state_trie(&self, addr: Address) -> Option<&MptNode> {
+        let witness = self.witness().ok()?;
+        witness.storage.get(&addr).map(|(trie, _)| trie)
+    }
+
+    // the witness is fetched on demand, nothing is missing upfront
+    fn check_witness(&self, _: &[BlockWithSenders]) -> DataProviderResult<()> {
+        Ok(())
+    }
+
+    fn get_acc<T: alloy_rlp::Decodable>(&self, addr: Address) -> Result<Option<T>, ProviderError> {
+        let proof = self.fetch_account(addr).map_err(provider_error)?;
+        let nonce = proof.nonce.to::<u64>();
+        let empty_code = proof.code_hash == KECCAK_EMPTY || proof.code_hash == B256::ZERO;
+        let empty_storage =
+            proof.storage_hash == EMPTY_ROOT_HASH || proof.storage_hash == B256::ZERO;
+        if nonce == 0 && proof.balance.is_zero() && empty_code && empty_storage {
+            return Ok(None);
+        }
+
+        let acc = StateAccount {
+            nonce,
+            balance: proof.balance,
+            storage_root: match empty_storage {
+                true => EMPTY_ROOT_HASH,
+                false => proof.storage_hash,
+            },
+            code_hash: match empty_code {
+                true => KECCAK_EMPTY,
+                false => proof.code_hash,
+            },
+        };
+        let data = alloy_rlp::encode(acc);
+        let acc = T::decode(&mut data.as_slice())
+            .map_err(|err| ProviderError::RPC(format!("decode account[{:?}]: {}", addr, err)))?;
+        Ok(Some(acc))
+    }
+
+    fn get_slot<T: alloy_rlp::Decodable>(
+        &self,
+        key: Address,
+        _root: B256,
+        slot: U256,
+    ) -> Result<Option<T>, ProviderError> {
+        let value = self.fetch_slot(key, slot).map_err(provider_error)?;
+        if value.is_zero() {
+            return Ok(None);
+        }
+        let data = alloy_rlp::encode(value);
+        let value = T::decode(&mut data.as_slice()).map_err(|err| {
+            ProviderError::RPC(format!("decode slot[{:?},{}]: {}", key, slot, err))
+        })?;
+        Ok(Some(value))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use executor::{decode_prefix, PruneKey, StateDiff};
+    use raiko_lib::primitives::mpt::{MptNodeData, MptNodeReference};
+    use reth_primitives::{keccak256, TransactionSigned, TxType};
+    use serde_json::json;
+
+    use super::*;
+    use crate::{testutil::load_request, Pob};
+
+    type Tries = (MptNode, HashMap<Address, StorageEntry>);
+
+    // the stand-in of the l2 node, it serves the parent state and the post state of
+    // the block from a ProofInput
+    struct NodeTransport {
+        input: ProofInput,
+        parent: Tries,
+        post: Tries,
+    }
+
+    impl NodeTransport {
+        fn new(input: ProofInput) -> Self {
+            let pob = Arc::new(Pob::try_from(input.clone()).unwrap());
+            let (_, diff) = BlockExecutor::new(pob).execute_with_diff().unwrap();
+            let parent = (
+                input.parent_state_trie.clone(),
+                input.parent_storage.clone(),
+            );
+            let post = apply_diff(parent.clone(), &diff);
+            assert_eq!(post.0.hash(), input.l2_block.state_root);
+            Self {
+                input,
+                parent,
+                post,
+            }
+        }
+
+        fn header(&self, number: u64) -> Option<&Header> {
+            let input = &self.input;
+            std::iter::once(&input.l2_block.header)
+                .chain(std::iter::once(&input.parent_header))
+                .chain(input.ancestor_headers.iter())
+                .find(|header| header.number == number)
+        }
+
+        fn tries(&self, tag: &Value) -> &Tries {
+            match parse_tag(tag) == self.input.l2_block.number {
+                true => &self.post,
+                false => &self.parent,
+            }
+        }
+
+        fn get_block(&self, params: &[Value]) -> Value {
+            let Some(header) = self.header(parse_tag(&params[0])) else {
+                return Value::Null;
+            };
+            let mut block = header_json(header);
+            let full = params[1].as_bool().unwrap();
+            if full && header.number == self.input.l2_block.number {
+                let block_hash = header.hash_slow();
+                let transactions = self
+                    .input
+                    .l2_block
+                    .body
+                    .iter()
+                    .enumerate()
+                    .map(|(idx, tx)| tx_json(tx, header.number, block_hash, idx))
+                    .collect::<Vec<_>>();
+                block["transactions"] = transactions.into();
+                block["withdrawals"] =
+                    serde_json::to_value(&self.input.l2_block.withdrawals).unwrap();
+            }
+            block
+        }
+
+        fn get_proof(&self, params: &[Value]) -> Value {
+            let addr: Address = serde_json::from_value(params[0].clone()).unwrap();
+            let slots: Vec<B256> = serde_json::from_value(params[1].clone()).unwrap();
+            let (state_trie, storage) = self.tries(&params[2]);
+
+            let key = keccak256(addr);
+            let account = state_trie
+                .get_rlp::<StateAccount>(key.as_slice())
+                .unwrap()
+                .unwrap_or(StateAccount {
+                    nonce: 0,
+                    balance: U256::ZERO,
+                    storage_root: EMPTY_ROOT_HASH,
+                    code_hash: KECCAK_EMPTY,
+                });
+            let storage_trie = storage.get(&addr).map(|(trie, _)| trie);
+            let storage_proof = slots
+                .iter()
+                .map(|slot| {
+                    let key = keccak256(slot);
+                    let (value, proof) = match storage_trie {
+                        Some(trie) => (
+                            trie.get_rlp::<U256>(key.as_slice()).unwrap(),
+                            proof_nodes(trie, key.as_slice()),
+                        ),
+                        None => (None, Vec::new()),
+                    };
+                    json!({
+                        "key": slot,
+                        "value": value.unwrap_or_default(),
+                        "proof": proof,
+                    })
+                })
+                .collect::<Vec<_>>();
+            json!({
+                "address": addr,
+                "balance": account.balance,
+                "codeHash": account.code_hash,
+                "nonce": format!("{:#x}", account.nonce),
+                "storageHash": account.storage_root,
+                "accountProof": proof_nodes(state_trie, key.as_slice()),
+                "storageProof": storage_proof,
+            })
+        }
+
+        fn get_code(&self, params: &[Value]) -> Value {
+            let addr: Address = serde_json::from_value(params[0].clone()).unwrap();
+            let (state_trie, _) = self.tries(&params[1]);
+            let code_hash = state_trie
+                .get_rlp::<StateAccount>(keccak256(addr).as_slice())
+                .unwrap()
+                .map(|account| account.code_hash);
+            let code = self
+                .input
+                .contracts
+                .iter()
+                .find(|code| Some(keccak256(code)) == code_hash)
+                .cloned()
+                .unwrap_or_default();
+            serde_json::to_value(code).unwrap()
+        }
+    }
+
+    impl RpcTransport for NodeTransport {
+        fn call(&self, method: &str, params: Vec<Value>) -> Result<Value, RpcProviderError> {
+            match method {
+                "eth_getBlockByNumber" => Ok(self.get_block(&params)),
+                "eth_getProof" => Ok(self.get_proof(&params)),
+                "eth_getCode" => Ok(self.get_code(&params)),
+                method => Err(RpcProviderError::Rpc {
+                    method: method.to_owned(),
+                    err: "method not found".into(),
+                }),
+            }
+        }
+    }
+
+    fn parse_tag(tag: &Value) -> u64 {
+        let tag = tag.as_str().unwrap();
+        u64::from_str_radix(tag.trim_start_matches("0x"), 16).unwrap()
+    }
+
+    fn apply_diff((mut state_trie, mut storage): Tries, diff: &StateDiff) -> Tries {
+        for (addr, account_diff) in &diff.accounts {
+            let key = keccak256(addr);
+            if account_diff.self_destructed {
+                state_trie.delete(key.as_slice()).unwrap();
+                storage.insert(*addr, (MptNode::default(), Vec::new()));
+                continue;
+            }
+            let mut account = match account_diff.created {
+                true => None,
+                false => state_trie.get_rlp::<StateAccount>(key.as_slice()).unwrap(),
+            }
+            .unwrap_or(StateAccount {
+                nonce: 0,
+                balance: U256::ZERO,
+                storage_root: EMPTY_ROOT_HASH,
+                code_hash: KECCAK_EMPTY,
+            });
+            let entry = storage.entry(*addr).or_default();
+            if account_diff.created {
+                entry.0 = MptNode::default();
+            }
+            for (slot, value) in &account_diff.storage {
+                let slot_key = keccak256(B256::from(slot.to_be_bytes::<32>()));
+                match value.to.is_zero() {
+                    true => entry.0.delete(slot_key.as_slice()).unwrap(),
+                    false => entry.0.insert_rlp(slot_key.as_slice(), value.to).unwrap(),
+                };
+            }
+            if let Some(balance) = &account_diff.balance {
+                account.balance = balance.to;
+            }
+            if let Some(nonce) = &account_diff.nonce {
+                account.nonce = nonce.to;
+            }
+            if let Some(code_hash) = &account_diff.code_hash {
+                account.code_hash = code_hash.to;
+            }
+            account.storage_root = entry.0.hash();
+            state_trie.insert_rlp(key.as_slice(), account).unwrap();
+        }
+        (state_trie, storage)
+    }
+
+    // the nodes walked through to look up the key, the ones shorter than 32 bytes
+    // are inlined into their parent
+    fn proof_nodes(root: &MptNode, key: &[u8]) -> Vec<Bytes> {
+        let nibs = PruneKey::new(key, false).nibs;
+        let mut nibs = nibs.as_slice();
+        let mut node = root;
+        let mut proof = vec![Bytes::from(alloy_rlp::encode(root))];
+        loop {
+            let child = match node.as_data() {
+                MptNodeData::Branch(children) => {
+                    let Some((idx, rest)) = nibs.split_first() else {
+                        break;
+                    };
+                    nibs = rest;
+                    children[*idx as usize].as_deref()
+                }
+                MptNodeData::Extension(prefix, child) => {
+                    let Some(rest) = nibs.strip_prefix(decode_prefix(prefix).as_slice()) else {
+                        break;
+                    };
+                    nibs = rest;
+                    Some(child.as_ref())
+                }
+                _ => None,
+            };
+            let Some(child) = child else {
+                break;
+            };
+            if let MptNodeData::Digest(_) = child.as_data() {
+                break;
+            }
+            if let MptNodeReference::Digest(_) = child.reference() {
+                proof.push(Bytes::from(alloy_rlp::encode(child)));
+            }
+            node = child;
+        }
+        proof
+    }
+
+    fn header_json(header: &Header) -> Value {
+        json!({
+            "hash": header.hash_slow(),
+            "parentHash": header.parent_hash,
+            "sha3Uncles": header.ommers_hash,
+            "miner": header.beneficiary,
+            "stateRoot": header.state_root,
+            "transactionsRoot": header.transactions_root,
+            "receiptsRoot": header.receipts_root,
+            "logsBloom": header.logs_bloom,
+            "difficulty": header.difficulty,
+            "number": format!("{:#x}", header.number),
+            "gasLimit": format!("{:#x}", header.gas_limit),
+            "gasUsed": format!("{:#x}", header.gas_used),
+            "timestamp": format!("{:#x}", header.timestamp),
+            "extraData": header.extra_data,
+            "mixHash": header.mix_hash,
+            "nonce": format!("{:#018x}", header.nonce),
+            "baseFeePerGas": header.base_fee_per_gas.map(|n| format!("{:#x}", n)),
+            "withdrawalsRoot": header.withdrawals_root,
+            "blobGasUsed": header.blob_gas_used.map(|n| format!("{:#x}", n)),
+            "excessBlobGas": header.excess_blob_gas.map(|n| format!("{:#x}", n)),
+            "parentBeaconBlockRoot": header.parent_beacon_block_root,
+            "requestsRoot": header.requests_root,
+            "uncles": [],
+            "transactions": [],
+        })
+    }
+
+    fn tx_json(tx: &TransactionSigned, number: u64, block_hash: B256, idx: usize) -> Value {
+        let signature = tx.signature();
+        let v = match tx.tx_type() {
+            TxType::Legacy => signature.v(tx.chain_id()),
+            _ => signature.odd_y_parity as u64,
+        };
+        let mut value = json!({
+            "hash": tx.hash(),
+            "nonce": format!("{:#x}", tx.nonce()),
+            "blockHash": block_hash,
+            "blockNumber": format!("{:#x}", number),
+            "transactionIndex": format!("{:#x}", idx),
+            "from": tx.recover_signer().unwrap(),
+            "to": tx.to(),
+            "value": tx.value(),
+            "gas": format!("{:#x}", tx.gas_limit()),
+            "input": tx.input(),
+            "r": signature.r,
+            "s": signature.s,
+            "v": format!("{:#x}", v),
+            "yParity": format!("{:#x}", signature.odd_y_parity as u8),
+            "chainId": tx.chain_id().map(|n| format!("{:#x}", n)),
+            "type": format!("{:#x}", u8::from(tx.tx_type())),
+        });
+        match tx.tx_type() {
+            TxType::Legacy | TxType::Eip2930 => {
+                value["gasPrice"] = format!("{:#x}", tx.max_fee_per_gas()).into();
+            }
+            _ => {
+                value["maxFeePerGas"] = format!("{:#x}", tx.max_fee_per_gas()).into();
+                value["maxPriorityFeePerGas"] = tx
+                    .max_priority_fee_per_gas()
+                    .map(|n| format!("{:#x}", n))
+                    .into();
+            }
+        }
+        if let Some(access_list) = tx.access_list() {
+            value["accessList"] = serde_json::to_value(access_list).unwrap();
+        }
+        value
+    }
+
+    #[test]
+    fn test_replay_recorded_proof_input() {
+        let input = load_request().input;
+        let block_hash = input.l2_block.hash_slow();
+
+        let node = RecordingTransport::new(NodeTransport::new(input.clone()));
+        let node = Arc::new(node);
+        let fetched = generate_proof_input(
+            node.clone(),
+            input.chain_spec.clone(),
+            input.l2_block.number,
+            input.taiko.clone(),
+        )
+        .unwrap();
+        assert_eq!(fetched.l2_block.hash_slow(), block_hash);
+
+        // the recorded responses are enough to rebuild the ProofInput without the node
+        let records = serde_json::to_vec(&node.records()).unwrap();
+        let transport = RecordedTransport::new(serde_json::from_slice(&records).unwrap());
+        let replayed = generate_proof_input(
+            Arc::new(transport),
+            input.chain_spec.clone(),
+            input.l2_block.number,
+            input.taiko.clone(),
+        )
+        .unwrap();
+        assert_eq!(
+            serde_json::to_value(&replayed).unwrap(),
+            serde_json::to_value(&fetched).unwrap()
+        );
+
+        let pob = Arc::new(Pob::try_from(replayed).unwrap());
+        let block = BlockExecutor::new(pob).execute().unwrap();
+        assert_eq!(block.header.hash_slow(), block_hash);
+    }
+
+    #[test]
+    fn test_genesis_block() {
+        let transport = RecordedTransport::new(Vec::new());
+        let req = load_request();
+        let err = generate_proof_input(
+            Arc::new(transport),
+            req.input.chain_spec,
+            0,
+            req.input.taiko,
+        );
+        assert!(err.is_err());
+    }
+}
@@ -0,0 +1,8 @@
+use crate::ProofRequest;
+
+// the request of block 48 of unifi-testnet, an ontake block whose body is the anchor tx
+// followed by one tx, it's shipped without the tx list
+pub fn load_request() -> ProofRequest {
+    let data = std::fs::read("../../testdata/proof-request-unifi-testnet-48.json").unwrap();
+    serde_json::from_slice(&data).unwrap()
+}
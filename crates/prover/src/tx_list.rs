@@ -0,0 +1,494 @@
+use std::io::Read;
+
+use alloy_rlp::Decodable;
+use base::stack_error;
+use kzg::kzg_types::ZFr;
+use kzg_traits::{
+    eip_4844::{blob_to_kzg_commitment_rust, bytes_to_blob},
+    G1,
+};
+use raiko_lib::primitives::eip4844::KZG_SETTINGS;
+use reth_primitives::{keccak256, Block, Bytes, TransactionSigned, B256};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::{BlockMetaDataFork, PobData};
+
+// the tx list the block is proposed with, the metadata commits to it by the blob hash
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TxList {
+    Calldata(Bytes),
+    Blob { blob: Bytes, commitment: Bytes },
+}
+
+stack_error! {
+    name: TxListError,
+    stack_name: TxListErrorStack,
+    error: {
+        BlobUsedMismatch{ want: bool, got: bool },
+        BlobHashMismatch{ want: B256, got: B256 },
+        CommitmentMismatch{ want: Bytes, got: Bytes },
+        Kzg(String),
+        TxListMismatch{ idx: usize, want: Option<B256>, got: Option<B256> },
+    },
+    wrap: {},
+    stack: {}
+}
+
+const BLOB_FIELD_ELEMENT_NUM: usize = 4096;
+const BLOB_FIELD_ELEMENT_BYTES: usize = 32;
+const BLOB_DATA_CAPACITY: usize = BLOB_FIELD_ELEMENT_NUM * BLOB_FIELD_ELEMENT_BYTES;
+const CALL_DATA_CAPACITY: usize = BLOB_FIELD_ELEMENT_NUM * (BLOB_FIELD_ELEMENT_BYTES - 1);
+const BLOB_ENCODING_VERSION: u8 = 0;
+const MAX_BLOB_DATA_SIZE: usize = (4 * 31 + 3) * 1024 - 4;
+const VERSIONED_HASH_VERSION_KZG: u8 = 1;
+
+// check the tx list against the metadata, and the txs of the block except the anchor
+// against the tx list. an undecodable tx list is an empty one by the taiko rules.
+//
+// the txs of the block must be the ones of the list the l2 node can include: the txs
+// with an invalid signature or another chain id are dropped, so are the txs over the
+// gas limit of the block. the gas pool only shrinks by the gas used, so any tx fitting
+// the limit alone may be included and nothing else is capped.
+pub fn verify_tx_list(block: &Block, data: &PobData, tx_list: &TxList) -> Result<(), TxListError> {
+    let (blob_hash, blob_used, blob_slice, gas_limit) = match &data.block_meta {
+        BlockMetaDataFork::None => return Ok(()),
+        BlockMetaDataFork::Hekla(meta) => {
+            (meta.blobHash, meta.blobUsed, None, meta.gasLimit as u64)
+        }
+        BlockMetaDataFork::Ontake(meta) => (
+            meta.blobHash,
+            meta.blobUsed,
+            Some((
+                meta.blobTxListOffset as usize,
+                meta.blobTxListLength as usize,
+            )),
+            meta.gasLimit as u64,
+        ),
+    };
+
+    let tx_data = match tx_list {
+        TxList::Calldata(calldata) => {
+            if blob_used {
+                return Err(TxListError::BlobUsedMismatch {
+                    want: blob_used,
+                    got: false,
+                });
+            }
+            let hash = keccak256(calldata);
+            if hash != blob_hash {
+                return Err(TxListError::BlobHashMismatch {
+                    want: blob_hash,
+                    got: hash,
+                });
+            }
+            // the calldata can't carry more than a blob
+            match calldata.len() > CALL_DATA_CAPACITY {
+                true => Vec::new(),
+                false => calldata.to_vec(),
+            }
+        }
+        TxList::Blob { blob, commitment } => {
+            if !blob_used {
+                return Err(TxListError::BlobUsedMismatch {
+                    want: blob_used,
+                    got: true,
+                });
+            }
+            let hash = commitment_to_versioned_hash(commitment);
+            if hash != blob_hash {
+                return Err(TxListError::BlobHashMismatch {
+                    want: blob_hash,
+                    got: hash,
+                });
+            }
+            let expected = blob_commitment(blob)?;
+            if expected != *commitment {
+                return Err(TxListError::CommitmentMismatch {
+                    want: expected,
+                    got: commitment.clone(),
+                });
+            }
+
+            let blob_data = decode_blob_data(blob);
+            // since ontake, multiple blocks can share one blob
+            match blob_slice {
+                Some((offset, length)) => blob_data
+                    .get(offset..offset.saturating_add(length))
+                    .map(|n| n.to_vec())
+                    .unwrap_or_default(),
+                None => blob_data,
+            }
+        }
+    };
+
+    let expected = decode_txs(&tx_data)
+        .into_iter()
+        .filter(|tx| is_includable(tx, data.chain_id, gas_limit))
+        .collect::<Vec<_>>();
+    let body = block.body.get(1..).unwrap_or_default();
+    for idx in 0..body.len().max(expected.len()) {
+        let want = expected.get(idx).map(|tx| tx.hash());
+        let got = body.get(idx).map(|tx| tx.hash());
+        if want != got {
+            return Err(TxListError::TxListMismatch {
+                idx: idx + 1,
+                want,
+                got,
+            });
+        }
+    }
+    Ok(())
+}
+
+// the txs without the chain id are left to the execution like the other txs
+fn is_includable(tx: &TransactionSigned, chain_id: u64, gas_limit: u64) -> bool {
+    tx.recover_signer().is_some()
+        && tx.chain_id().map_or(true, |id| id == chain_id)
+        && tx.gas_limit() <= gas_limit
+}
+
+fn commitment_to_versioned_hash(commitment: &[u8]) -> B256 {
+    let mut hash: [u8; 32] = Sha256::digest(commitment).into();
+    hash[0] = VERSIONED_HASH_VERSION_KZG;
+    hash.into()
+}
+
+fn blob_commitment(blob: &[u8]) -> Result<Bytes, TxListError> {
+    let blob: Vec<ZFr> = bytes_to_blob(blob).map_err(TxListError::Kzg)?;
+    let commitment =
+        blob_to_kzg_commitment_rust(&blob, &*KZG_SETTINGS).map_err(TxListError::Kzg)?;
+    Ok(commitment.to_bytes().to_vec().into())
+}
+
+// zlib compressed rlp list of the txs
+fn decode_txs(data: &[u8]) -> Vec<TransactionSigned> {
+    let mut decoder = match libflate::zlib::Decoder::new(data) {
+        Ok(decoder) => decoder,
+        Err(_) => return Vec::new(),
+    };
+    let mut buf = Vec::new();
+    if decoder.read_to_end(&mut buf).is_err() {
+        return Vec::new();
+    }
+    Vec::<TransactionSigned>::decode(&mut buf.as_slice()).unwrap_or_default()
+}
+
+// the blob encoding used by the taiko proposer: every 4 field elements carry
+// 127 bytes, the first 5 bytes are the version and the length of the data.
+// an invalid blob decodes to empty data.
+fn decode_blob_data(blob: &[u8]) -> Vec<u8> {
+    if blob.len() != BLOB_DATA_CAPACITY || blob[1] != BLOB_ENCODING_VERSION {
+        return Vec::new();
+    }
+    let output_len = u32::from_be_bytes([0, blob[2], blob[3], blob[4]]) as usize;
+    if output_len > MAX_BLOB_DATA_SIZE {
+        return Vec::new();
+    }
+
+    let mut output = vec![0; MAX_BLOB_DATA_SIZE];
+    // round 0 only has 27 bytes of data in the first field element
+    output[0..27].copy_from_slice(&blob[5..32]);
+    let mut opos = 28;
+    let mut ipos = 32;
+    let mut encoded = [blob[0], 0, 0, 0];
+    for byte in encoded.iter_mut().skip(1) {
+        let Some(n) = decode_field_element(blob, opos, ipos, &mut output) else {
+            return Vec::new();
+        };
+        (*byte, opos, ipos) = n;
+    }
+    opos = reassemble_bytes(opos, &encoded, &mut output);
+
+    for _ in 1..1024 {
+        if opos >= output_len {
+            break;
+        }
+        for byte in encoded.iter_mut() {
+            let Some(n) = decode_field_element(blob, opos, ipos, &mut output) else {
+                return Vec::new();
+            };
+            (*byte, opos, ipos) = n;
+        }
+        opos = reassemble_bytes(opos, &encoded, &mut output);
+    }
+
+    // the padding must be zero
+    if output[output_len..].iter().any(|n| *n != 0) || blob[ipos..].iter().any(|n| *n != 0) {
+        return Vec::new();
+    }
+    output.truncate(output_len);
+    output
+}
+
+fn decode_field_element(
+    blob: &[u8],
+    opos: usize,
+    ipos: usize,
+    output: &mut [u8],
+) -> Option<(u8, usize, usize)> {
+    // the two highest bits of a field element are always 0
+    if blob[ipos] & 0b1100_0000 != 0 {
+        return None;
+    }
+    output[opos..opos + 31].copy_from_slice(&blob[ipos + 1..ipos + 32]);
+    Some((blob[ipos], opos + 32, ipos + 32))
+}
+
+fn reassemble_bytes(opos: usize, encoded: &[u8; 4], output: &mut [u8]) -> usize {
+    // there is no 128th byte in a round
+    let opos = opos - 1;
+    let x = (encoded[0] & 0b0011_1111) | ((encoded[1] & 0b0011_0000) << 2);
+    let y = (encoded[1] & 0b0000_1111) | ((encoded[3] & 0b0000_1111) << 4);
+    let z = (encoded[2] & 0b0011_1111) | ((encoded[3] & 0b0011_0000) << 2);
+    output[opos - 32] = z;
+    output[opos - 32 * 2] = y;
+    output[opos - 32 * 3] = x;
+    opos
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::Write;
+
+    use raiko_lib::input::ontake::BlockMetadataV2;
+    use reth_primitives::{sign_message, U256};
+
+    use super::*;
+    use crate::{testutil::load_request, Pob};
+
+    // the inverse of `decode_blob_data`
+    fn encode_blob_data(data: &[u8]) -> Vec<u8> {
+        let mut input = vec![BLOB_ENCODING_VERSION];
+        input.extend_from_slice(&(data.len() as u32).to_be_bytes()[1..]);
+        input.extend_from_slice(data);
+        // every round carries 4 * 31 + 3 bytes in 4 field elements
+        input.resize(input.len().div_ceil(127) * 127, 0);
+
+        let mut blob = vec![0; BLOB_DATA_CAPACITY];
+        for (round, chunk) in input.chunks(127).enumerate() {
+            let out = &mut blob[round * 128..(round + 1) * 128];
+            let (x, y, z) = (chunk[31], chunk[63], chunk[95]);
+            out[0] = x & 0b0011_1111;
+            out[32] = (y & 0b0000_1111) | ((x & 0b1100_0000) >> 2);
+            out[64] = z & 0b0011_1111;
+            out[96] = ((z & 0b1100_0000) >> 2) | ((y & 0b1111_0000) >> 4);
+            for idx in 0..4 {
+                out[idx * 32 + 1..idx * 32 + 32].copy_from_slice(&chunk[idx * 32..idx * 32 + 31]);
+            }
+        }
+        blob
+    }
+
+    fn encode_txs(txs: &[TransactionSigned]) -> Vec<u8> {
+        let mut encoder = libflate::zlib::Encoder::new(Vec::new()).unwrap();
+        encoder.write_all(&alloy_rlp::encode(txs.to_vec())).unwrap();
+        encoder.finish().into_result().unwrap()
+    }
+
+    // the block of unifi-testnet-48, an anchor tx followed by one tx
+    fn load_pob() -> Pob {
+        Pob::try_from(load_request().input).unwrap()
+    }
+
+    fn ontake_meta(pob: &Pob) -> BlockMetadataV2 {
+        match &pob.data.block_meta {
+            BlockMetaDataFork::Ontake(meta) => meta.clone(),
+            _ => unreachable!(),
+        }
+    }
+
+    // the metadata commits to the tx list in the calldata
+    fn with_calldata(mut pob: Pob, data: &[u8]) -> Pob {
+        pob.data.block_meta = BlockMetaDataFork::Ontake(BlockMetadataV2 {
+            blobHash: keccak256(data),
+            blobUsed: false,
+            ..ontake_meta(&pob)
+        });
+        pob
+    }
+
+    fn verify_calldata(pob: &Pob, txs: &[TransactionSigned]) -> Result<(), TxListError> {
+        let data = encode_txs(txs);
+        let pob = with_calldata(pob.clone(), &data);
+        verify_tx_list(&pob.block, &pob.data, &TxList::Calldata(data.into()))
+    }
+
+    #[test]
+    fn test_decode_blob_data() {
+        let data = (0..100_000).map(|n| (n * 7) as u8).collect::<Vec<_>>();
+        let blob = encode_blob_data(&data);
+        assert_eq!(decode_blob_data(&blob), data);
+        assert_eq!(decode_blob_data(&encode_blob_data(&[])), Vec::<u8>::new());
+
+        // the highest bits of a field element are set
+        let mut invalid = blob.clone();
+        invalid[32] |= 0b1000_0000;
+        assert!(decode_blob_data(&invalid).is_empty());
+
+        // the padding isn't zero
+        let mut invalid = encode_blob_data(&data[..100]);
+        invalid[BLOB_DATA_CAPACITY - 1] = 1;
+        assert!(decode_blob_data(&invalid).is_empty());
+
+        let mut invalid = blob.clone();
+        invalid[1] = BLOB_ENCODING_VERSION + 1;
+        assert!(decode_blob_data(&invalid).is_empty());
+        assert!(decode_blob_data(&blob[1..]).is_empty());
+    }
+
+    #[test]
+    fn test_verify_tx_list_blob_slice() {
+        let pob = load_pob();
+        let tx_list = encode_txs(&pob.block.body[1..]);
+
+        // the tx list of the block is in the middle of the blob shared with other blocks
+        let offset = 1000;
+        let mut data = vec![0xff; offset];
+        data.extend_from_slice(&tx_list);
+        data.extend_from_slice(&[0xee; 1000]);
+        let blob = Bytes::from(encode_blob_data(&data));
+        let commitment = blob_commitment(&blob).unwrap();
+        let meta = BlockMetadataV2 {
+            blobHash: commitment_to_versioned_hash(&commitment),
+            blobUsed: true,
+            blobTxListOffset: offset as u32,
+            blobTxListLength: tx_list.len() as u32,
+            ..ontake_meta(&pob)
+        };
+        let with_meta = |meta: BlockMetadataV2| {
+            let mut data = pob.data.clone();
+            data.block_meta = BlockMetaDataFork::Ontake(meta);
+            data
+        };
+        let tx_list = TxList::Blob {
+            blob: blob.clone(),
+            commitment: commitment.clone(),
+        };
+        verify_tx_list(&pob.block, &with_meta(meta.clone()), &tx_list).unwrap();
+
+        // the slice doesn't cover the tx list, it decodes to no tx
+        let shifted = BlockMetadataV2 {
+            blobTxListOffset: offset as u32 + 1,
+            ..meta.clone()
+        };
+        assert!(matches!(
+            verify_tx_list(&pob.block, &with_meta(shifted), &tx_list),
+            Err(TxListError::TxListMismatch {
+                idx: 1,
+                want: None,
+                ..
+            })
+        ));
+
+        // the commitment is the one of another blob
+        let other = Bytes::from(encode_blob_data(&data[1..]));
+        let tx_list = TxList::Blob {
+            blob: other,
+            commitment: commitment.clone(),
+        };
+        assert!(matches!(
+            verify_tx_list(&pob.block, &with_meta(meta.clone()), &tx_list),
+            Err(TxListError::CommitmentMismatch { got, .. }) if got == commitment
+        ));
+
+        // the commitment isn't the one committed by the metadata
+        let other_commitment = blob_commitment(&encode_blob_data(&data[1..])).unwrap();
+        let tx_list = TxList::Blob {
+            blob,
+            commitment: other_commitment,
+        };
+        assert!(matches!(
+            verify_tx_list(&pob.block, &with_meta(meta), &tx_list),
+            Err(TxListError::BlobHashMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn test_verify_tx_list() {
+        let pob = load_pob();
+        let anchor = pob.block.body[0].clone();
+        let tx = pob.block.body[1].clone();
+        verify_calldata(&pob, &[tx.clone()]).unwrap();
+
+        // a valid tx of the list is missing from the block
+        assert!(matches!(
+            verify_calldata(&pob, &[tx.clone(), anchor.clone()]),
+            Err(TxListError::TxListMismatch { idx: 2, want: Some(want), got: None })
+                if want == anchor.hash()
+        ));
+        assert!(matches!(
+            verify_calldata(&pob, &[anchor.clone(), tx.clone()]),
+            Err(TxListError::TxListMismatch { idx: 1, .. })
+        ));
+
+        // the txs are out of order
+        let mut reordered = pob.clone();
+        reordered.block.body = vec![anchor.clone(), anchor.clone(), tx.clone()];
+        assert!(matches!(
+            verify_calldata(&reordered, &[tx.clone(), anchor.clone()]),
+            Err(TxListError::TxListMismatch { idx: 1, .. })
+        ));
+
+        // an undecodable tx list is an empty one
+        let data = vec![1, 2, 3];
+        let tx_list = TxList::Calldata(data.clone().into());
+        let mut empty = with_calldata(pob.clone(), &data);
+        assert!(matches!(
+            verify_tx_list(&empty.block, &empty.data, &tx_list),
+            Err(TxListError::TxListMismatch {
+                idx: 1,
+                want: None,
+                ..
+            })
+        ));
+        empty.block.body.truncate(1);
+        verify_tx_list(&empty.block, &empty.data, &tx_list).unwrap();
+    }
+
+    #[test]
+    fn test_verify_tx_list_dropped_txs() {
+        let pob = load_pob();
+        let tx = pob.block.body[1].clone();
+
+        let mut invalid_signature = tx.clone();
+        invalid_signature.signature.r = U256::ZERO;
+        invalid_signature.hash = invalid_signature.recalculate_hash();
+
+        let mut other_chain = tx.transaction.clone();
+        other_chain.set_chain_id(pob.data.chain_id + 1);
+        let signature =
+            sign_message(B256::with_last_byte(1), other_chain.signature_hash()).unwrap();
+        let other_chain = TransactionSigned::from_transaction_and_signature(other_chain, signature);
+
+        let txs = [invalid_signature.clone(), tx.clone(), other_chain.clone()];
+        verify_calldata(&pob, &txs).unwrap();
+
+        // the dropped txs can't be included
+        let mut included = pob.clone();
+        included.block.body.push(other_chain);
+        assert!(matches!(
+            verify_calldata(&included, &txs),
+            Err(TxListError::TxListMismatch {
+                idx: 2,
+                want: None,
+                ..
+            })
+        ));
+
+        // the tx is over the gas limit of the block
+        let mut over_gas = pob.clone();
+        over_gas.data.block_meta = BlockMetaDataFork::Ontake(BlockMetadataV2 {
+            gasLimit: tx.gas_limit() as u32 - 1,
+            ..ontake_meta(&pob)
+        });
+        assert!(matches!(
+            verify_calldata(&over_gas, &[tx.clone()]),
+            Err(TxListError::TxListMismatch { idx: 1, want: None, got: Some(got) })
+                if got == tx.hash()
+        ));
+        over_gas.block.body.truncate(1);
+        verify_calldata(&over_gas, &[tx]).unwrap();
+    }
+}
@@ -0,0 +1,206 @@
+use base::{stack_error, Keypair};
+use raiko_lib::primitives::keccak::keccak;
+use reth_primitives::{Address, B256, U256};
+
+use crate::{meta_hash, Pob, Poe, SignedPoe};
+
+stack_error! {
+    name: VerifyError,
+    stack_name: VerifyErrorStack,
+    error: {
+        InvalidPackedLength{ want: usize, got: usize },
+        InvalidSignatureLength{ want: usize, got: usize },
+        InvalidSignature,
+        InstanceMismatch{ want: Address, got: Address },
+    },
+    wrap: {},
+    stack: {}
+}
+
+pub const PACKED_PROOF_LEN: usize = 4 + 20 + 65;
+
+// what the signed message commits to besides the poe, as ProverRegistryVerifier
+// rebuilds it from the proof submitted on L1
+#[derive(Debug, Clone)]
+pub struct PoeContext {
+    pub chain_id: u64,
+    pub prover_registry: Address,
+    pub prover: Address,
+    pub meta_hash: B256,
+    // the instance attested with the id on the registry, the signer must be it if given
+    pub attested_instance: Option<Address>,
+}
+
+impl PoeContext {
+    pub fn from_pob(pob: &Pob, prover_registry: Address) -> Self {
+        Self {
+            chain_id: pob.data.chain_id,
+            prover_registry,
+            prover: pob.data.prover,
+            meta_hash: meta_hash(&pob.data.block_meta),
+            attested_instance: None,
+        }
+    }
+}
+
+// the instance recovered from the signature, it's up to the caller to check
+// it's registered with the id on the registry unless the context has it.
+// the new instance is the key the prover rotates to, it needn't be the signer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VerifiedPoe {
+    pub id: U256,
+    pub instance: Address,
+    pub new_instance: Address,
+}
+
+// rebuild the SignedPoe from the packed ProofResponse.data of /v1/get_proof,
+// the poe isn't part of the packed data and the tee type isn't signed.
+pub fn unpack_signed_poe(poe: Poe, data: &[u8], tee_type: U256) -> Result<SignedPoe, VerifyError> {
+    if data.len() != PACKED_PROOF_LEN {
+        return Err(VerifyError::InvalidPackedLength {
+            want: PACKED_PROOF_LEN,
+            got: data.len(),
+        });
+    }
+    Ok(SignedPoe {
+        poe,
+        id: U256::from_be_slice(&data[..4]),
+        new_instance: Address::from_slice(&data[4..24]),
+        signature: data[24..].to_vec().into(),
+        teeType: tee_type,
+    })
+}
+
+// recover the signer the same way as ProverRegistryVerifier, the signer is the
+// attested instance and the new instance is only part of the signed message.
+pub fn verify_signed_poe(
+    signed_poe: &SignedPoe,
+    ctx: &PoeContext,
+) -> Result<VerifiedPoe, VerifyError> {
+    let signature: &[u8; 65] = signed_poe.signature.as_ref().try_into().map_err(|_| {
+        VerifyError::InvalidSignatureLength {
+            want: 65,
+            got: signed_poe.signature.len(),
+        }
+    })?;
+    let msg = signed_poe.poe.signed_msg_by_parts(
+        ctx.chain_id,
+        ctx.prover_registry,
+        signed_poe.new_instance,
+        ctx.prover,
+        ctx.meta_hash,
+    );
+    let instance = Keypair::recover_digest_ecdsa(keccak(msg), signature)
+        .ok_or(VerifyError::InvalidSignature)?;
+    if let Some(attested) = ctx.attested_instance {
+        if instance != attested {
+            return Err(VerifyError::InstanceMismatch {
+                want: attested,
+                got: instance,
+            });
+        }
+    }
+    Ok(VerifiedPoe {
+        id: signed_poe.id,
+        instance,
+        new_instance: signed_poe.new_instance,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use reth_primitives::keccak256;
+
+    #[test]
+    fn test_verify_round_trip() {
+        let kp = Keypair::new();
+        let id = U256::from(7);
+        let instance = kp.address();
+        let ctx = PoeContext {
+            chain_id: 167009,
+            prover_registry: Address::repeat_byte(1),
+            prover: Address::repeat_byte(2),
+            meta_hash: keccak256([3u8]),
+            attested_instance: None,
+        };
+        let poe = Poe {
+            parent_hash: B256::repeat_byte(4),
+            block_hash: B256::repeat_byte(5),
+            state_root: B256::repeat_byte(6),
+            graffiti: B256::ZERO,
+        };
+        let msg = poe.signed_msg_by_parts(
+            ctx.chain_id,
+            ctx.prover_registry,
+            instance,
+            ctx.prover,
+            ctx.meta_hash,
+        );
+        let sig = Keypair::sign_digest_ecdsa(&kp.secret_key(), keccak(msg));
+        let packed = [&id.to_be_bytes::<32>()[28..], instance.as_slice(), &sig[..]].concat();
+
+        let signed_poe = unpack_signed_poe(poe.clone(), &packed, U256::from(1)).unwrap();
+        let verified = verify_signed_poe(&signed_poe, &ctx).unwrap();
+        assert_eq!(verified.id, id);
+        assert_eq!(verified.instance, instance);
+
+        let ctx = PoeContext { chain_id: 1, ..ctx };
+        assert!(verify_signed_poe(&signed_poe, &ctx).is_err());
+    }
+
+    #[test]
+    fn test_verify_rotated_instance() {
+        // signed by the attested instance, rotating to another key
+        let kp = Keypair::new();
+        let new_instance = Keypair::new().address();
+        let ctx = PoeContext {
+            chain_id: 167009,
+            prover_registry: Address::repeat_byte(1),
+            prover: Address::repeat_byte(2),
+            meta_hash: keccak256([3u8]),
+            attested_instance: None,
+        };
+        let poe = Poe {
+            parent_hash: B256::repeat_byte(4),
+            block_hash: B256::repeat_byte(5),
+            state_root: B256::repeat_byte(6),
+            graffiti: B256::ZERO,
+        };
+        let msg = poe.signed_msg_by_parts(
+            ctx.chain_id,
+            ctx.prover_registry,
+            new_instance,
+            ctx.prover,
+            ctx.meta_hash,
+        );
+        let signed_poe = SignedPoe {
+            poe,
+            id: U256::from(7),
+            new_instance,
+            signature: Keypair::sign_digest_ecdsa(&kp.secret_key(), keccak(msg))
+                .to_vec()
+                .into(),
+            teeType: U256::from(1),
+        };
+
+        let verified = verify_signed_poe(&signed_poe, &ctx).unwrap();
+        assert_eq!(verified.instance, kp.address());
+        assert_eq!(verified.new_instance, new_instance);
+
+        let attested = PoeContext {
+            attested_instance: Some(kp.address()),
+            ..ctx.clone()
+        };
+        assert_eq!(verify_signed_poe(&signed_poe, &attested).unwrap(), verified);
+
+        let attested = PoeContext {
+            attested_instance: Some(new_instance),
+            ..ctx
+        };
+        assert!(matches!(
+            verify_signed_poe(&signed_poe, &attested),
+            Err(VerifyError::InstanceMismatch { got, .. }) if got == kp.address()
+        ));
+    }
+}